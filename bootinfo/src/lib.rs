@@ -0,0 +1,281 @@
+#![no_std]
+
+use core::fmt;
+use kalloc::MemorySegment;
+
+/// Information handed from the bootloader to the kernel.
+/// All addresses are physical and all memory referenced is identity mapped at kernel entry.
+#[repr(C)]
+pub struct BootInfo {
+    /// Always `BootInfo::MAGIC`
+    pub magic: u64,
+    /// The layout version, must equal `BootInfo::VERSION`
+    pub version: u32,
+    /// The size of this structure as seen by the bootloader
+    pub size: u32,
+    pub memory_map: Slice<MemorySegment>,
+    pub framebuffer: Framebuffer,
+    /// The ACPI RSDP, or zero if the firmware did not provide one
+    pub rsdp: u64,
+    /// The SMBIOS 2.x entry point, or zero
+    pub smbios: u64,
+    /// The SMBIOS 3.x entry point, or zero
+    pub smbios3: u64,
+    /// UTF-8 kernel command line
+    pub command_line: Slice<u8>,
+    /// The initial ramdisk archive, in pages the kernel's allocator leaves alone
+    pub initrd: Slice<u8>,
+    pub boot_time: Time,
+    /// The UEFI runtime services table in the kernel's address space, or zero if unavailable
+    pub runtime_services: u64,
+    /// Random bytes for seeding the kernel's own generator
+    pub seed: [u8; 32],
+    /// Where the seed came from, and so how far it can be trusted
+    pub seed_source: EntropySource,
+    /// How far the kernel was moved from its link address, zero unless it was randomised
+    pub kernel_slide: u64,
+    /// Every logical processor the firmware knows of, or empty if it could not say and the ACPI MADT must be used
+    pub processors: Slice<Processor>
+}
+impl BootInfo {
+    pub const MAGIC: u64 = u64::from_le_bytes(*b"CHERIMOY");
+    /// Incremented whenever the layout of `BootInfo` or anything it references changes
+    pub const VERSION: u32 = 7;
+
+    pub const fn new() -> Self {
+        Self {
+            magic: Self::MAGIC,
+            version: Self::VERSION,
+            size: core::mem::size_of::<Self>() as u32,
+            memory_map: Slice::EMPTY,
+            framebuffer: Framebuffer::NONE,
+            rsdp: 0,
+            smbios: 0,
+            smbios3: 0,
+            command_line: Slice::EMPTY,
+            initrd: Slice::EMPTY,
+            boot_time: Time::UNKNOWN,
+            runtime_services: 0,
+            seed: [0; 32],
+            seed_source: EntropySource::None,
+            kernel_slide: 0,
+            processors: Slice::EMPTY
+        }
+    }
+    /// Check that the boot info was produced by a compatible bootloader.
+    /// Only the magic and version are read before they are validated.
+    /// # Safety
+    /// `boot_info` must be null or point to readable memory at least 16 bytes long
+    pub unsafe fn validate<'a>(boot_info: *const Self) -> Result<&'a Self, Error> {
+        if boot_info.is_null() {
+            return Err(Error::Null)
+        }
+        let magic = core::ptr::read(&(*boot_info).magic);
+        if magic != Self::MAGIC {
+            return Err(Error::Magic(magic))
+        }
+        let version = core::ptr::read(&(*boot_info).version);
+        if version != Self::VERSION {
+            return Err(Error::Version(version))
+        }
+        Ok(&*boot_info)
+    }
+    pub fn memory_map(&self) -> &[MemorySegment] {
+        unsafe { self.memory_map.as_slice() }
+    }
+    /// The command line, or `None` if it is not valid UTF-8
+    pub fn command_line(&self) -> Option<&str> {
+        core::str::from_utf8(unsafe { self.command_line.as_slice() }).ok()
+    }
+    /// The initial ramdisk, empty if none was loaded
+    pub fn initrd(&self) -> &[u8] {
+        unsafe { self.initrd.as_slice() }
+    }
+    pub fn processors(&self) -> &[Processor] {
+        unsafe { self.processors.as_slice() }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Null,
+    Magic(u64),
+    Version(u32)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "no boot info was passed to the kernel"),
+            Self::Magic(magic) => write!(f, "boot info has bad magic number {:#018x}", magic),
+            Self::Version(version) => write!(f, "boot info version {} does not match the kernel's version {}, the bootloader and kernel must be updated together", version, BootInfo::VERSION)
+        }
+    }
+}
+
+/// A pointer and length pair with a stable layout
+#[repr(C)]
+pub struct Slice<T> {
+    pub address: *const T,
+    pub len: usize
+}
+impl<T> Slice<T> {
+    pub const EMPTY: Self = Self { address: core::ptr::null(), len: 0 };
+
+    /// # Safety
+    /// The slice must reference `len` valid elements, or be empty
+    pub unsafe fn as_slice(&self) -> &[T] {
+        if self.len == 0 {
+            &[]
+        } else {
+            core::slice::from_raw_parts(self.address, self.len)
+        }
+    }
+}
+impl<T> From<&[T]> for Slice<T> {
+    fn from(slice: &[T]) -> Self {
+        Self {
+            address: slice.as_ptr(),
+            len: slice.len()
+        }
+    }
+}
+
+/// Sources of boot-time randomness, from least to most trustworthy
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum EntropySource {
+    /// The seed is all zeroes
+    None,
+    /// Jitter in the timestamp counter, which is weak and should be mixed with anything better the kernel finds
+    Timing,
+    /// The RDRAND instruction
+    Rdrand,
+    /// The RDSEED instruction
+    Rdseed,
+    /// The firmware's EFI_RNG_PROTOCOL
+    Firmware
+}
+
+/// A logical processor and where it sits in the package, core and thread topology
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct Processor {
+    /// The local APIC ID, used to address the processor with interrupts
+    pub apic_id: u32,
+    pub flags: u32,
+    pub package: u32,
+    pub core: u32,
+    pub thread: u32
+}
+impl Processor {
+    /// The bootstrap processor, which enters the kernel
+    pub const BOOTSTRAP: u32 = 1 << 0;
+    /// Usable, as opposed to disabled by the firmware
+    pub const ENABLED: u32 = 1 << 1;
+    /// Passed its built-in self test
+    pub const HEALTHY: u32 = 1 << 2;
+
+    #[inline]
+    pub fn bootstrap(&self) -> bool {
+        self.flags & Self::BOOTSTRAP != 0
+    }
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.flags & Self::ENABLED != 0
+    }
+    #[inline]
+    pub fn healthy(&self) -> bool {
+        self.flags & Self::HEALTHY != 0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PixelFormat {
+    /// 8 bits per channel, red in the lowest byte
+    Rgbx,
+    /// 8 bits per channel, blue in the lowest byte
+    Bgrx,
+    /// Described by the channel masks
+    Bitmask
+}
+
+/// A linear framebuffer with 32-bit pixels
+#[repr(C)]
+pub struct Framebuffer {
+    /// Zero if no framebuffer is available
+    pub base: u64,
+    pub size: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Pixels per scanline, which may be more than `width`
+    pub stride: u32,
+    pub format: PixelFormat,
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32
+}
+impl Framebuffer {
+    pub const NONE: Self = Self {
+        base: 0,
+        size: 0,
+        width: 0,
+        height: 0,
+        stride: 0,
+        format: PixelFormat::Bgrx,
+        red_mask: 0,
+        green_mask: 0,
+        blue_mask: 0
+    };
+
+    #[inline]
+    pub fn present(&self) -> bool {
+        self.base != 0
+    }
+}
+
+/// Wall clock time at boot
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct Time {
+    /// Zero if the time is unknown
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    _pad: u8,
+    pub nanosecond: u32,
+    /// Local time minus UTC in minutes
+    pub timezone: i16,
+    _pad2: u16
+}
+impl Time {
+    pub const fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8, nanosecond: u32, timezone: i16) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            _pad: 0,
+            nanosecond,
+            timezone,
+            _pad2: 0
+        }
+    }
+    pub const UNKNOWN: Self = Self {
+        year: 0,
+        month: 0,
+        day: 0,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        _pad: 0,
+        nanosecond: 0,
+        timezone: 0,
+        _pad2: 0
+    };
+}
@@ -0,0 +1,35 @@
+//! Embeds the Ed25519 public key that kernels must be signed with.
+//! The key is the raw 32 byte public key at `CHERIMOYA_PUBLIC_KEY`, or `keys/kernel.pub` in the repository by default.
+
+use std::{env, fs, path::PathBuf};
+
+const KEY_SIZE: usize = 32;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=CHERIMOYA_PUBLIC_KEY");
+    let path = match env::var_os("CHERIMOYA_PUBLIC_KEY") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("../keys/kernel.pub")
+    };
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let key = match fs::read(&path) {
+        Ok(key) => key,
+        Err(error) => panic!(
+            "cannot read the kernel signing public key at {}: {}\n\
+            Run `./run` from the repository root to create a development key pair, \
+            or set CHERIMOYA_PUBLIC_KEY to the path of a raw {} byte Ed25519 public key",
+            path.display(), error, KEY_SIZE
+        )
+    };
+    if key.len() != KEY_SIZE {
+        panic!(
+            "the kernel signing public key at {} is {} bytes, expected a raw {} byte Ed25519 public key\n\
+            One can be extracted from a private key with `openssl pkey -in kernel.key -pubout -outform DER | tail -c 32`",
+            path.display(), key.len(), KEY_SIZE
+        )
+    }
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("kernel.pub");
+    fs::write(out, key).unwrap();
+}
@@ -0,0 +1,581 @@
+//! The boot configuration read from the boot volume
+//! ```text
+//! # Comments run to the end of the line
+//! default = "Cherimoya"
+//! timeout = 3
+//! video = 1024x768
+//! verbosity = info
+//! mirror = false
+//! kaslr = true
+//! panic = wait
+//!
+//! entry "Cherimoya" {
+//!     kernel = "\kernel"
+//!     initrd = "\init"
+//!     cmdline = "console=ttyS0"
+//! }
+//! ```
+//! `timeout` is in seconds, or `forever` to wait for a choice.
+//! `mirror` also writes the log to the firmware console as well as the serial port.
+//! `kaslr` loads a position-independent kernel at a random address.
+//! `panic` is what to do after reporting a bootloader panic: `reset` at once, `wait` for a key press first, or `halt`.
+//! Strings have no escapes and may not contain `"`.
+
+use core::fmt;
+use crate::{log::Level, menu::Entry, panic};
+
+/// Path of the configuration file on the boot volume
+pub const PATH: &str = "\\cherimoya.cfg";
+/// Most entries a configuration can list
+pub const MAX_ENTRIES: usize = 16;
+const DEFAULT_TIMEOUT: Option<u32> = Some(3);
+/// How to recover from a panic when the configuration does not say
+pub const DEFAULT_PANIC_ACTION: panic::Action = panic::Action::WaitForKey;
+
+pub struct Config<'a> {
+    entries: [Entry<'a>; MAX_ENTRIES],
+    len: usize,
+    /// Index of the entry booted when the timeout expires
+    pub default: usize,
+    /// Seconds before booting the default entry, or `None` to wait for a choice
+    pub timeout: Option<u32>,
+    /// Resolution to use if the firmware supports it, otherwise the highest available is used
+    pub video: Option<(u32, u32)>,
+    /// The least important log messages to keep
+    pub verbosity: Level,
+    /// Copy log messages to the firmware console
+    pub mirror: bool,
+    /// Move a position-independent kernel by a random slide
+    pub kaslr: bool,
+    /// How to recover from a bootloader panic
+    pub panic: panic::Action
+}
+impl<'a> Config<'a> {
+    const EMPTY_ENTRY: Entry<'static> = Entry {
+        name: "",
+        kernel: "",
+        initrd: None,
+        command_line: ""
+    };
+
+    /// Used when the boot volume has no configuration file
+    pub fn fallback() -> Config<'static> {
+        let mut entries = [Self::EMPTY_ENTRY; MAX_ENTRIES];
+        entries[0] = Entry {
+            name: "Cherimoya",
+            kernel: "\\kernel",
+            initrd: None,
+            command_line: ""
+        };
+        Config {
+            entries,
+            len: 1,
+            default: 0,
+            timeout: DEFAULT_TIMEOUT,
+            video: None,
+            verbosity: Level::Info,
+            mirror: false,
+            kaslr: false,
+            panic: DEFAULT_PANIC_ACTION
+        }
+    }
+    #[inline]
+    pub fn entries(&self) -> &[Entry<'a>] {
+        &self.entries[..self.len]
+    }
+
+    /// Parse a configuration file, rejecting anything not understood
+    pub fn parse(text: &'a [u8]) -> Result<Self, Error> {
+        let text = core::str::from_utf8(text).map_err(|error| {
+            let valid = &text[..error.valid_up_to()];
+            let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+            let start = valid.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            // Safe: everything before `valid_up_to` is valid UTF-8
+            let column = unsafe { core::str::from_utf8_unchecked(&valid[start..]) }.chars().count() + 1;
+            Error { line, column, kind: ErrorKind::Encoding }
+        })?;
+
+        let mut entries = [Self::EMPTY_ENTRY; MAX_ENTRIES];
+        let mut len = 0;
+        let mut default = None;
+        let mut timeout = None;
+        let mut video = None;
+        let mut verbosity = None;
+        let mut mirror = None;
+        let mut kaslr = None;
+        let mut panic = None;
+        let mut entry: Option<Partial> = None;
+        let mut lines = 0;
+
+        for (index, line) in text.lines().enumerate() {
+            lines = index + 1;
+            let mut tokens = Tokens::new(line, lines);
+            let first = match tokens.next()? {
+                Some(first) => first,
+                None => continue
+            };
+            match (first.token, &mut entry) {
+                (Token::Word("entry"), None) => {
+                    let name = tokens.string()?;
+                    tokens.expect(Token::Open, "`{`")?;
+                    tokens.end()?;
+                    entry = Some(Partial {
+                        name: name.0,
+                        line: lines,
+                        column: first.column,
+                        kernel: None,
+                        initrd: None,
+                        command_line: None
+                    });
+                },
+                (Token::Close, Some(partial)) => {
+                    tokens.end()?;
+                    let kernel = partial.kernel.ok_or_else(|| tokens.error(first.column, ErrorKind::MissingKernel))?;
+                    if entries[..len].iter().any(|entry| entry.name == partial.name) {
+                        return Err(Error { line: partial.line, column: partial.column, kind: ErrorKind::DuplicateEntry })
+                    }
+                    *entries.get_mut(len).ok_or(Error { line: partial.line, column: partial.column, kind: ErrorKind::TooManyEntries })? = Entry {
+                        name: partial.name,
+                        kernel,
+                        initrd: partial.initrd,
+                        command_line: partial.command_line.unwrap_or("")
+                    };
+                    len += 1;
+                    entry = None;
+                },
+                (Token::Word(key), Some(partial)) => {
+                    tokens.expect(Token::Equals, "`=`")?;
+                    match key {
+                        "kernel" => set(&mut partial.kernel, tokens.string()?.0, &tokens, first.column)?,
+                        "initrd" => set(&mut partial.initrd, tokens.string()?.0, &tokens, first.column)?,
+                        "cmdline" => set(&mut partial.command_line, tokens.string()?.0, &tokens, first.column)?,
+                        _ => return Err(tokens.error(first.column, ErrorKind::UnknownKey))
+                    }
+                    tokens.end()?;
+                },
+                (Token::Word(key), None) => {
+                    tokens.expect(Token::Equals, "`=`")?;
+                    match key {
+                        "default" => {
+                            let (name, column) = tokens.string()?;
+                            set(&mut default, (name, lines, column), &tokens, first.column)?
+                        },
+                        "timeout" => set(&mut timeout, tokens.timeout()?, &tokens, first.column)?,
+                        "video" => set(&mut video, tokens.resolution()?, &tokens, first.column)?,
+                        "verbosity" => set(&mut verbosity, tokens.verbosity()?, &tokens, first.column)?,
+                        "mirror" => set(&mut mirror, tokens.boolean()?, &tokens, first.column)?,
+                        "kaslr" => set(&mut kaslr, tokens.boolean()?, &tokens, first.column)?,
+                        "panic" => set(&mut panic, tokens.panic_action()?, &tokens, first.column)?,
+                        _ => return Err(tokens.error(first.column, ErrorKind::UnknownKey))
+                    }
+                    tokens.end()?;
+                },
+                (_, Some(_)) => return Err(tokens.error(first.column, ErrorKind::Expected("a key or `}`"))),
+                (_, None) => return Err(tokens.error(first.column, ErrorKind::Expected("a key or `entry`")))
+            }
+        }
+
+        if let Some(partial) = entry {
+            return Err(Error { line: partial.line, column: partial.column, kind: ErrorKind::UnclosedEntry })
+        }
+        if len == 0 {
+            return Err(Error { line: lines + 1, column: 1, kind: ErrorKind::NoEntries })
+        }
+        let default = match default {
+            Some((name, line, column)) => entries[..len].iter()
+                .position(|entry| entry.name == name)
+                .ok_or(Error { line, column, kind: ErrorKind::UnknownEntry })?,
+            None => 0
+        };
+        Ok(Self {
+            entries,
+            len,
+            default,
+            timeout: timeout.unwrap_or(DEFAULT_TIMEOUT),
+            video,
+            verbosity: verbosity.unwrap_or(Level::Info),
+            mirror: mirror.unwrap_or(false),
+            kaslr: kaslr.unwrap_or(false),
+            panic: panic.unwrap_or(DEFAULT_PANIC_ACTION)
+        })
+    }
+}
+
+/// Where and why a configuration file was rejected
+#[derive(Copy, Clone, Debug)]
+pub struct Error {
+    pub line: usize,
+    /// Column in characters, starting from 1
+    pub column: usize,
+    pub kind: ErrorKind
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    /// The file is not valid UTF-8
+    Encoding,
+    UnterminatedString,
+    UnexpectedCharacter(char),
+    Expected(&'static str),
+    UnknownKey,
+    DuplicateKey,
+    DuplicateEntry,
+    TooManyEntries,
+    MissingKernel,
+    UnclosedEntry,
+    /// The default names an entry that does not exist
+    UnknownEntry,
+    NoEntries
+}
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Encoding => f.write_str("invalid UTF-8"),
+            Self::UnterminatedString => f.write_str("unterminated string"),
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            Self::Expected(expected) => write!(f, "expected {}", expected),
+            Self::UnknownKey => f.write_str("unknown key"),
+            Self::DuplicateKey => f.write_str("key is already set"),
+            Self::DuplicateEntry => f.write_str("an entry with this name already exists"),
+            Self::TooManyEntries => write!(f, "more than {} entries", MAX_ENTRIES),
+            Self::MissingKernel => f.write_str("entry has no kernel"),
+            Self::UnclosedEntry => f.write_str("entry is never closed"),
+            Self::UnknownEntry => f.write_str("no entry with this name"),
+            Self::NoEntries => f.write_str("no entries")
+        }
+    }
+}
+
+/// An entry whose closing brace has not been reached
+struct Partial<'a> {
+    name: &'a str,
+    line: usize,
+    column: usize,
+    kernel: Option<&'a str>,
+    initrd: Option<&'a str>,
+    command_line: Option<&'a str>
+}
+
+/// Set a key that may only appear once
+fn set<T>(slot: &mut Option<T>, value: T, tokens: &Tokens, column: usize) -> Result<(), Error> {
+    if slot.is_some() {
+        return Err(tokens.error(column, ErrorKind::DuplicateKey))
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    String(&'a str),
+    Equals,
+    Open,
+    Close
+}
+#[derive(Copy, Clone)]
+struct Spanned<'a> {
+    token: Token<'a>,
+    column: usize
+}
+
+/// Splits a single line into tokens
+struct Tokens<'a> {
+    line: &'a str,
+    number: usize,
+    offset: usize
+}
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str, number: usize) -> Self {
+        Self {
+            line,
+            number,
+            offset: 0
+        }
+    }
+    fn error(&self, column: usize, kind: ErrorKind) -> Error {
+        Error {
+            line: self.number,
+            column,
+            kind
+        }
+    }
+    /// The column of the next unread character
+    fn column(&self) -> usize {
+        self.line[..self.offset].chars().count() + 1
+    }
+    fn next(&mut self) -> Result<Option<Spanned<'a>>, Error> {
+        let rest = &self.line[self.offset..];
+        let trimmed = rest.trim_start();
+        self.offset += rest.len() - trimmed.len();
+        let column = self.column();
+
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.';
+        let (token, len) = match trimmed.chars().next() {
+            None | Some('#') => return Ok(None),
+            Some('=') => (Token::Equals, 1),
+            Some('{') => (Token::Open, 1),
+            Some('}') => (Token::Close, 1),
+            Some('"') => {
+                let end = trimmed[1..].find('"').ok_or(self.error(column, ErrorKind::UnterminatedString))?;
+                (Token::String(&trimmed[1..end + 1]), end + 2)
+            },
+            Some(c) if is_word(c) => {
+                let len = trimmed.find(|c| !is_word(c)).unwrap_or(trimmed.len());
+                (Token::Word(&trimmed[..len]), len)
+            },
+            Some(c) => return Err(self.error(column, ErrorKind::UnexpectedCharacter(c)))
+        };
+        self.offset += len;
+        Ok(Some(Spanned { token, column }))
+    }
+    /// The next token, which must exist
+    fn token(&mut self, expected: &'static str) -> Result<Spanned<'a>, Error> {
+        let column = self.column();
+        self.next()?.ok_or(self.error(column, ErrorKind::Expected(expected)))
+    }
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), Error> {
+        let next = self.token(expected)?;
+        if next.token != token {
+            return Err(self.error(next.column, ErrorKind::Expected(expected)))
+        }
+        Ok(())
+    }
+    /// Require that nothing but a comment is left on the line
+    fn end(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            Some(next) => Err(self.error(next.column, ErrorKind::Expected("the end of the line"))),
+            None => Ok(())
+        }
+    }
+    /// A quoted string and its column
+    fn string(&mut self) -> Result<(&'a str, usize), Error> {
+        const EXPECTED: &str = "a quoted string";
+        match self.token(EXPECTED)? {
+            Spanned { token: Token::String(string), column } => Ok((string, column)),
+            Spanned { column, .. } => Err(self.error(column, ErrorKind::Expected(EXPECTED)))
+        }
+    }
+    fn word(&mut self, expected: &'static str) -> Result<(&'a str, usize), Error> {
+        match self.token(expected)? {
+            Spanned { token: Token::Word(word), column } => Ok((word, column)),
+            Spanned { column, .. } => Err(self.error(column, ErrorKind::Expected(expected)))
+        }
+    }
+    fn timeout(&mut self) -> Result<Option<u32>, Error> {
+        const EXPECTED: &str = "a number of seconds or `forever`";
+        match self.word(EXPECTED)? {
+            ("forever", _) => Ok(None),
+            (seconds, column) => seconds.parse().map(Some).map_err(|_| self.error(column, ErrorKind::Expected(EXPECTED)))
+        }
+    }
+    fn resolution(&mut self) -> Result<(u32, u32), Error> {
+        const EXPECTED: &str = "a resolution such as `1024x768`";
+        let (resolution, column) = self.word(EXPECTED)?;
+        let error = self.error(column, ErrorKind::Expected(EXPECTED));
+        let (width, height) = resolution.split_once('x').ok_or(error)?;
+        let dimension = |value: &str| match value.parse() {
+            Ok(value) if value > 0 => Ok(value),
+            _ => Err(error)
+        };
+        Ok((dimension(width)?, dimension(height)?))
+    }
+    fn verbosity(&mut self) -> Result<Level, Error> {
+        const EXPECTED: &str = "one of `error`, `warn`, `info`, `debug` or `trace`";
+        match self.word(EXPECTED)? {
+            ("error", _) => Ok(Level::Error),
+            ("warn", _) => Ok(Level::Warn),
+            ("info", _) => Ok(Level::Info),
+            ("debug", _) => Ok(Level::Debug),
+            ("trace", _) => Ok(Level::Trace),
+            (_, column) => Err(self.error(column, ErrorKind::Expected(EXPECTED)))
+        }
+    }
+    fn panic_action(&mut self) -> Result<panic::Action, Error> {
+        const EXPECTED: &str = "one of `reset`, `wait` or `halt`";
+        match self.word(EXPECTED)? {
+            ("reset", _) => Ok(panic::Action::Reset),
+            ("wait", _) => Ok(panic::Action::WaitForKey),
+            ("halt", _) => Ok(panic::Action::Halt),
+            (_, column) => Err(self.error(column, ErrorKind::Expected(EXPECTED)))
+        }
+    }
+    fn boolean(&mut self) -> Result<bool, Error> {
+        const EXPECTED: &str = "`true` or `false`";
+        match self.word(EXPECTED)? {
+            ("true", _) => Ok(true),
+            ("false", _) => Ok(false),
+            (_, column) => Err(self.error(column, ErrorKind::Expected(EXPECTED)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> Error {
+        match Config::parse(text.as_bytes()) {
+            Ok(_) => panic!("{:?} was accepted", text),
+            Err(error) => error
+        }
+    }
+    /// The kind, line and column of the error in `text`
+    fn rejects(text: &str) -> (ErrorKind, usize, usize) {
+        let error = error(text);
+        (error.kind, error.line, error.column)
+    }
+    fn rejects_bytes(text: &[u8]) -> (ErrorKind, usize, usize) {
+        let error = Config::parse(text).err().expect("invalid UTF-8 was accepted");
+        (error.kind, error.line, error.column)
+    }
+    const ENTRY: &str = "entry \"A\" {\n    kernel = \"\\a\"\n}\n";
+
+    #[test]
+    fn full() {
+        let text = "# A comment\n\
+            default = \"B\"\n\
+            timeout = 5 # seconds\n\
+            video = 1024x768\n\
+            verbosity = trace\n\
+            mirror = true\n\
+            kaslr = true\n\
+            panic = halt\n\
+            \n\
+            entry \"A\" {\n\
+                kernel = \"\\a\"\n\
+            }\n\
+            entry \"B\" {\n\
+                kernel = \"\\b\"\n\
+                initrd = \"\\initrd\"\n\
+                cmdline = \"console=ttyS0 quiet\"\n\
+            }\n";
+        let config = Config::parse(text.as_bytes()).unwrap();
+        assert_eq!(config.entries().len(), 2);
+        assert_eq!(config.default, 1);
+        assert_eq!(config.timeout, Some(5));
+        assert_eq!(config.video, Some((1024, 768)));
+        assert_eq!(config.verbosity, Level::Trace);
+        assert!(config.mirror);
+        assert!(config.kaslr);
+        assert!(config.panic == panic::Action::Halt);
+
+        let a = &config.entries()[0];
+        assert_eq!((a.name, a.kernel, a.initrd, a.command_line), ("A", "\\a", None, ""));
+        let b = &config.entries()[1];
+        assert_eq!((b.name, b.kernel, b.initrd, b.command_line), ("B", "\\b", Some("\\initrd"), "console=ttyS0 quiet"));
+    }
+    #[test]
+    fn defaults() {
+        let config = Config::parse(ENTRY.as_bytes()).unwrap();
+        assert_eq!(config.entries().len(), 1);
+        assert_eq!(config.default, 0);
+        assert_eq!(config.timeout, DEFAULT_TIMEOUT);
+        assert_eq!(config.video, None);
+        assert_eq!(config.verbosity, Level::Info);
+        assert!(!config.mirror);
+        assert!(!config.kaslr);
+        assert!(config.panic == DEFAULT_PANIC_ACTION);
+    }
+    /// A field of the configuration made from `line` and a valid entry
+    fn field<T>(line: &str, field: impl Fn(&Config) -> T) -> Result<T, ErrorKind> {
+        let text = format!("{}\n{}", line, ENTRY);
+        Config::parse(text.as_bytes()).map(|config| field(&config)).map_err(|error| error.kind)
+    }
+    #[test]
+    fn values() {
+        assert_eq!(field("timeout = forever", |config| config.timeout), Ok(None));
+        assert_eq!(field("timeout = 0", |config| config.timeout), Ok(Some(0)));
+        assert_eq!(field("verbosity = warn", |config| config.verbosity), Ok(Level::Warn));
+        assert!(field("panic = reset", |config| config.panic == panic::Action::Reset).unwrap());
+        assert!(field("panic = wait", |config| config.panic == panic::Action::WaitForKey).unwrap());
+        for line in &["timeout = -1", "timeout = soon", "mirror = yes", "verbosity = loud", "panic = explode"] {
+            assert!(matches!(field(line, |_| ()), Err(ErrorKind::Expected(_))), "{}", line);
+        }
+    }
+    #[test]
+    fn video() {
+        assert_eq!(field("video = 800x600", |config| config.video), Ok(Some((800, 600))));
+        for line in &["video = 800", "video = 800x", "video = x600", "video = 0x600", "video = 800x600x32", "video = 800X600", "video = \"800x600\""] {
+            assert!(matches!(field(line, |_| ()), Err(ErrorKind::Expected(_))), "{}", line);
+        }
+        assert_eq!(rejects("video = 800*600\n"), (ErrorKind::Expected("a resolution such as `1024x768`"), 1, 9));
+    }
+    #[test]
+    fn encoding() {
+        let mut text = b"timeout = 3\nvideo = ab".to_vec();
+        text.push(0xFF);
+        assert_eq!(rejects_bytes(&text), (ErrorKind::Encoding, 2, 11));
+    }
+    #[test]
+    fn unterminated_string() {
+        assert_eq!(rejects("default = \"A\n"), (ErrorKind::UnterminatedString, 1, 11));
+    }
+    #[test]
+    fn unexpected_character() {
+        assert_eq!(rejects("timeout = 3;\n"), (ErrorKind::UnexpectedCharacter(';'), 1, 12));
+        // Columns count characters rather than bytes
+        assert_eq!(rejects("entry \"é\" { @\n"), (ErrorKind::UnexpectedCharacter('@'), 1, 13));
+    }
+    #[test]
+    fn expected() {
+        assert_eq!(rejects("timeout 3\n"), (ErrorKind::Expected("`=`"), 1, 9));
+        assert_eq!(rejects("timeout =\n"), (ErrorKind::Expected("a number of seconds or `forever`"), 1, 10));
+        assert_eq!(rejects("timeout = 3 4\n"), (ErrorKind::Expected("the end of the line"), 1, 13));
+        assert_eq!(rejects("entry A {\n"), (ErrorKind::Expected("a quoted string"), 1, 7));
+        assert_eq!(rejects("entry \"A\"\n"), (ErrorKind::Expected("`{`"), 1, 10));
+        assert_eq!(rejects("}\n"), (ErrorKind::Expected("a key or `entry`"), 1, 1));
+        assert_eq!(rejects("entry \"A\" {\n    entry \"B\" {\n"), (ErrorKind::Expected("`=`"), 2, 11));
+        assert_eq!(rejects("entry \"A\" {\n    \"kernel\" = \"\\a\"\n"), (ErrorKind::Expected("a key or `}`"), 2, 5));
+    }
+    #[test]
+    fn unknown_key() {
+        assert_eq!(rejects("colour = red\n"), (ErrorKind::UnknownKey, 1, 1));
+        assert_eq!(rejects("entry \"A\" {\n  timeout = 3\n}\n"), (ErrorKind::UnknownKey, 2, 3));
+        assert_eq!(rejects(&format!("{}kernel = \"\\a\"\n", ENTRY)), (ErrorKind::UnknownKey, 4, 1));
+    }
+    #[test]
+    fn duplicate_key() {
+        assert_eq!(rejects("timeout = 3\ntimeout = 4\n"), (ErrorKind::DuplicateKey, 2, 1));
+        assert_eq!(rejects("entry \"A\" {\n    kernel = \"\\a\"\n    kernel = \"\\b\"\n}\n"), (ErrorKind::DuplicateKey, 3, 5));
+    }
+    #[test]
+    fn duplicate_entry() {
+        assert_eq!(rejects(&format!("{}{}", ENTRY, ENTRY)), (ErrorKind::DuplicateEntry, 4, 1));
+    }
+    #[test]
+    fn too_many_entries() {
+        let mut text = String::new();
+        for i in 0..MAX_ENTRIES {
+            text += &format!("entry \"{}\" {{\n    kernel = \"\\k\"\n}}\n", i);
+        }
+        assert_eq!(Config::parse(text.as_bytes()).unwrap().entries().len(), MAX_ENTRIES);
+        text += "entry \"one more\" {\n    kernel = \"\\k\"\n}\n";
+        assert_eq!(rejects(&text), (ErrorKind::TooManyEntries, MAX_ENTRIES * 3 + 1, 1));
+    }
+    #[test]
+    fn missing_kernel() {
+        assert_eq!(rejects("entry \"A\" {\n    initrd = \"\\i\"\n  }\n"), (ErrorKind::MissingKernel, 3, 3));
+    }
+    #[test]
+    fn unclosed_entry() {
+        assert_eq!(rejects("timeout = 3\n  entry \"A\" {\n    kernel = \"\\a\"\n"), (ErrorKind::UnclosedEntry, 2, 3));
+    }
+    #[test]
+    fn unknown_entry() {
+        assert_eq!(rejects(&format!("default = \"B\"\n{}", ENTRY)), (ErrorKind::UnknownEntry, 1, 11));
+    }
+    #[test]
+    fn no_entries() {
+        assert_eq!(rejects(""), (ErrorKind::NoEntries, 1, 1));
+        assert_eq!(rejects("timeout = 3\n# nothing else\n"), (ErrorKind::NoEntries, 3, 1));
+    }
+    #[test]
+    fn display() {
+        assert_eq!(error("timeout = 3\ntimeout = 4\n").to_string(), "line 2, column 1: key is already set");
+    }
+}
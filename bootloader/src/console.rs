@@ -0,0 +1,78 @@
+use core::fmt::{self, Write};
+use crate::uefi::{self, SystemTable, protocol::console::{Key, Keystroke}};
+
+/// The system table while boot services are available, null once they have exited
+static mut SYSTEM_TABLE: *mut SystemTable = core::ptr::null_mut();
+
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::console::print(format_args!($($arg)*)));
+}
+macro_rules! println {
+    () => (print!("\n"));
+    ($($arg:tt)*) => (print!("{}\n", format_args!($($arg)*)));
+}
+
+/// Direct `print!` to the firmware console
+pub fn init(system_table: &mut SystemTable) {
+    unsafe { SYSTEM_TABLE = system_table }
+}
+/// Stop using the firmware console, which is gone once boot services exit
+pub fn exit() {
+    unsafe { SYSTEM_TABLE = core::ptr::null_mut() }
+}
+/// The system table if boot services are still available
+/// # Safety
+/// The caller must not hold on to the reference past `console::exit`
+pub unsafe fn system_table() -> Option<&'static mut SystemTable> {
+    SYSTEM_TABLE.as_mut()
+}
+
+/// Block until a key is pressed
+pub fn read_key() -> Result<Key, uefi::Error> {
+    let system_table = unsafe { system_table() }.ok_or(uefi::Error::Unsupported)?;
+    system_table.stdin.read_key(system_table.boot_services)
+}
+
+/// Edit a line of text in place, starting with the `len` bytes already in `buffer`.
+/// Enter accepts the line, escape abandons the edit and returns `None`.
+/// Characters that do not fit in the buffer are ignored.
+pub fn edit_line(buffer: &mut [u8], mut len: usize) -> Result<Option<&str>, uefi::Error> {
+    let initial = core::str::from_utf8(&buffer[..len]).map_err(|_| uefi::Error::InvalidParameter)?;
+    print!("{}", initial);
+    loop {
+        match read_key()?.decode() {
+            Keystroke::Enter => {
+                println!();
+                // Safe: only whole characters are ever added or removed from valid UTF-8
+                return Ok(Some(unsafe { core::str::from_utf8_unchecked(&buffer[..len]) }))
+            },
+            Keystroke::Escape => {
+                println!();
+                return Ok(None)
+            },
+            Keystroke::Backspace => {
+                // Step back over any continuation bytes to the start of the last character
+                if len > 0 {
+                    len -= 1;
+                    while len > 0 && buffer[len] & 0xC0 == 0x80 {
+                        len -= 1;
+                    }
+                    print!("\u{8} \u{8}");
+                }
+            },
+            Keystroke::Char(c) if !c.is_control() && len + c.len_utf8() <= buffer.len() => {
+                c.encode_utf8(&mut buffer[len..]);
+                len += c.len_utf8();
+                print!("{}", c);
+            },
+            _ => ()
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn print(args: fmt::Arguments) {
+    if let Some(system_table) = unsafe { system_table() } {
+        let _ = system_table.stdout.write_fmt(args);
+    }
+}
@@ -0,0 +1,4 @@
+//! Cryptography for checking what the bootloader loads, in pure Rust so that it does not depend on the firmware
+
+mod sha512;
+pub mod ed25519;
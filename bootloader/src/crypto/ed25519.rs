@@ -0,0 +1,430 @@
+//! Ed25519 signature verification as specified in RFC 8032.
+//! Only public data is handled so nothing here needs to run in constant time.
+
+use core::fmt;
+use super::sha512::Sha512;
+
+pub const PUBLIC_KEY_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The public key is not the encoding of a curve point
+    PublicKey,
+    /// The signature is malformed
+    Encoding,
+    /// The signature does not match the message and key
+    Mismatch
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PublicKey => write!(f, "the public key is invalid"),
+            Self::Encoding => write!(f, "the signature is malformed"),
+            Self::Mismatch => write!(f, "the signature does not match")
+        }
+    }
+}
+
+/// Check that `signature` was made over `message` by the holder of the private half of `public_key`.
+/// Signatures with a non-canonical `S` are rejected.
+pub fn verify(public_key: &[u8; PUBLIC_KEY_SIZE], message: &[u8], signature: &[u8; SIGNATURE_SIZE]) -> Result<(), Error> {
+    let a = Point::decode(public_key).ok_or(Error::PublicKey)?;
+    let mut r = [0; 32];
+    r.copy_from_slice(&signature[..32]);
+    let mut s = [0; 32];
+    s.copy_from_slice(&signature[32..]);
+    if !scalar_is_canonical(&s) {
+        return Err(Error::Encoding)
+    }
+    // R only has to match once encoded, but it must still be a point
+    Point::decode(&r).ok_or(Error::Encoding)?;
+
+    let mut hash = Sha512::new();
+    hash.update(&r);
+    hash.update(public_key);
+    hash.update(message);
+    let k = reduce(&hash.finish());
+
+    // [S]B = R + [k]A, checked as [S]B - [k]A = R
+    let check = Point::base().multiply(&s).add(&a.negate().multiply(&k));
+    if check.encode() == r {
+        Ok(())
+    } else {
+        Err(Error::Mismatch)
+    }
+}
+
+/// The order of the base point, little-endian
+const L: [u64; 4] = [0x5812631a5cf5d3ed, 0x14def9dea2f79cd6, 0, 0x1000000000000000];
+
+fn scalar_is_canonical(s: &[u8; 32]) -> bool {
+    let s = words(s);
+    for i in (0..4).rev() {
+        if s[i] != L[i] {
+            return s[i] < L[i]
+        }
+    }
+    false
+}
+/// Reduce a 512-bit little-endian number modulo `L` one bit at a time
+fn reduce(value: &[u8; 64]) -> [u8; 32] {
+    // Stays below 2L, which fits in 254 bits
+    let mut r = [0u64; 4];
+    for bit in (0..512).rev() {
+        r[3] = r[3] << 1 | r[2] >> 63;
+        r[2] = r[2] << 1 | r[1] >> 63;
+        r[1] = r[1] << 1 | r[0] >> 63;
+        r[0] = r[0] << 1 | (value[bit / 8] >> (bit % 8) & 1) as u64;
+        if !less_than(&r, &L) {
+            let mut borrow = 0;
+            for i in 0..4 {
+                let (difference, b1) = r[i].overflowing_sub(L[i]);
+                let (difference, b2) = difference.overflowing_sub(borrow);
+                r[i] = difference;
+                borrow = (b1 | b2) as u64;
+            }
+        }
+    }
+    let mut bytes = [0; 32];
+    for (word, chunk) in r.iter().zip(bytes.chunks_exact_mut(8)) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+fn less_than(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i]
+        }
+    }
+    false
+}
+fn words(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut words = [0; 4];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7]]);
+    }
+    words
+}
+
+/// An element of the field of integers modulo 2^255 - 19 in five 51-bit limbs.
+/// Limbs may grow a few bits past 51 between reductions.
+#[derive(Copy, Clone)]
+struct Field([u64; 5]);
+impl Field {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+    const MASK: u64 = (1 << 51) - 1;
+
+    /// Decode a little-endian number, ignoring the top bit
+    fn decode(bytes: &[u8; 32]) -> Self {
+        let w = words(bytes);
+        Self([
+            w[0] & Self::MASK,
+            (w[0] >> 51 | w[1] << 13) & Self::MASK,
+            (w[1] >> 38 | w[2] << 26) & Self::MASK,
+            (w[2] >> 25 | w[3] << 39) & Self::MASK,
+            w[3] >> 12 & Self::MASK
+        ])
+    }
+    /// The unique little-endian encoding of the fully reduced element
+    fn encode(&self) -> [u8; 32] {
+        let mut h = self.carry().carry().0;
+        // Subtract p if h >= p by adding 19 and dropping bit 255
+        let mut q = (h[0] + 19) >> 51;
+        for limb in &h[1..] {
+            q = (limb + q) >> 51;
+        }
+        h[0] += 19 * q;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= Self::MASK;
+        }
+        h[4] &= Self::MASK;
+
+        let w = [
+            h[0] | h[1] << 51,
+            h[1] >> 13 | h[2] << 38,
+            h[2] >> 26 | h[3] << 25,
+            h[3] >> 39 | h[4] << 12
+        ];
+        let mut bytes = [0; 32];
+        for (word, chunk) in w.iter().zip(bytes.chunks_exact_mut(8)) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+    /// Whether the fully reduced element is odd, which RFC 8032 calls negative
+    fn is_negative(&self) -> bool {
+        self.encode()[0] & 1 != 0
+    }
+    fn is_zero(&self) -> bool {
+        self.encode() == [0; 32]
+    }
+    fn equals(&self, other: &Self) -> bool {
+        self.encode() == other.encode()
+    }
+
+    /// Move the excess of each limb into the next, folding the top back in as 2^255 = 19
+    fn carry(&self) -> Self {
+        let mut h = self.0;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= Self::MASK;
+        }
+        h[0] += 19 * (h[4] >> 51);
+        h[4] &= Self::MASK;
+        Self(h)
+    }
+    fn add(&self, other: &Self) -> Self {
+        let mut h = self.0;
+        for (limb, other) in h.iter_mut().zip(other.0.iter()) {
+            *limb += other;
+        }
+        Self(h).carry()
+    }
+    fn sub(&self, other: &Self) -> Self {
+        // Adding 4p first keeps every limb positive
+        const FOUR_P: [u64; 5] = [0x1FFFFFFFFFFFB4, 0x1FFFFFFFFFFFFC, 0x1FFFFFFFFFFFFC, 0x1FFFFFFFFFFFFC, 0x1FFFFFFFFFFFFC];
+        let mut h = self.0;
+        for i in 0..5 {
+            h[i] = h[i] + FOUR_P[i] - other.0[i];
+        }
+        Self(h).carry()
+    }
+    fn negate(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+    fn mul(&self, other: &Self) -> Self {
+        let a = self.0;
+        let b = other.0;
+        let m = |x: u64, y: u64| x as u128 * y as u128;
+        // Products past the top limb wrap around multiplied by 19
+        let b19 = [b[0] * 19, b[1] * 19, b[2] * 19, b[3] * 19, b[4] * 19];
+        let mut r = [
+            m(a[0], b[0]) + m(a[1], b19[4]) + m(a[2], b19[3]) + m(a[3], b19[2]) + m(a[4], b19[1]),
+            m(a[0], b[1]) + m(a[1], b[0]) + m(a[2], b19[4]) + m(a[3], b19[3]) + m(a[4], b19[2]),
+            m(a[0], b[2]) + m(a[1], b[1]) + m(a[2], b[0]) + m(a[3], b19[4]) + m(a[4], b19[3]),
+            m(a[0], b[3]) + m(a[1], b[2]) + m(a[2], b[1]) + m(a[3], b[0]) + m(a[4], b19[4]),
+            m(a[0], b[4]) + m(a[1], b[3]) + m(a[2], b[2]) + m(a[3], b[1]) + m(a[4], b[0])
+        ];
+        for i in 0..4 {
+            r[i + 1] += r[i] >> 51;
+            r[i] &= Self::MASK as u128;
+        }
+        r[0] += 19 * (r[4] >> 51);
+        r[4] &= Self::MASK as u128;
+        r[1] += r[0] >> 51;
+        r[0] &= Self::MASK as u128;
+        Self([r[0] as u64, r[1] as u64, r[2] as u64, r[3] as u64, r[4] as u64]).carry()
+    }
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+    /// Raise to a little-endian exponent
+    fn pow(&self, exponent: &[u8; 32]) -> Self {
+        let mut result = Self::ONE;
+        for bit in (0..256).rev() {
+            result = result.square();
+            if exponent[bit / 8] >> (bit % 8) & 1 != 0 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+    /// The multiplicative inverse, by Fermat's little theorem as x^(p - 2)
+    fn invert(&self) -> Self {
+        self.pow(&exponent(0xEB, 0x7F))
+    }
+
+    /// The curve constant d = -121665 / 121666
+    fn d() -> Self {
+        Self::decode(&[
+            0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+            0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52
+        ])
+    }
+    /// A square root of -1, 2^((p - 1) / 4)
+    fn sqrt_m1() -> Self {
+        Self::decode(&[
+            0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+            0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b
+        ])
+    }
+}
+/// An exponent of the form 2^n - c, all ones apart from its lowest and highest bytes
+fn exponent(low: u8, high: u8) -> [u8; 32] {
+    let mut exponent = [0xFF; 32];
+    exponent[0] = low;
+    exponent[31] = high;
+    exponent
+}
+
+/// A point on the twisted Edwards curve in extended coordinates, x = X/Z, y = Y/Z and xy = T/Z
+#[derive(Copy, Clone)]
+struct Point {
+    x: Field,
+    y: Field,
+    z: Field,
+    t: Field
+}
+impl Point {
+    const IDENTITY: Self = Self {
+        x: Field::ZERO,
+        y: Field::ONE,
+        z: Field::ONE,
+        t: Field::ZERO
+    };
+
+    /// The base point B, whose y coordinate is 4/5 and x coordinate is positive
+    fn base() -> Self {
+        let mut encoded = [0x66; 32];
+        encoded[0] = 0x58;
+        Self::decode(&encoded).expect("the base point is on the curve")
+    }
+    /// Decode a point as described in RFC 8032 section 5.1.3, rejecting a non-canonical y coordinate
+    fn decode(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 != 0;
+        let y = Field::decode(bytes);
+        let mut canonical = y.encode();
+        canonical[31] |= bytes[31] & 0x80;
+        if canonical != *bytes {
+            return None
+        }
+
+        // x^2 = (y^2 - 1) / (dy^2 + 1), so x = u/v with the candidate root (u/v)^((p + 3) / 8) = uv^3(uv^7)^((p - 5) / 8)
+        let y2 = y.square();
+        let u = y2.sub(&Field::ONE);
+        let v = Field::d().mul(&y2).add(&Field::ONE);
+        let v3 = v.square().mul(&v);
+        let v7 = v3.square().mul(&v);
+        let mut x = u.mul(&v3).mul(&u.mul(&v7).pow(&exponent(0xFD, 0x0F)));
+
+        let vx2 = v.mul(&x.square());
+        if vx2.equals(&u) {
+        } else if vx2.equals(&u.negate()) {
+            x = x.mul(&Field::sqrt_m1());
+        } else {
+            return None
+        }
+        if x.is_zero() && sign {
+            return None
+        }
+        if x.is_negative() != sign {
+            x = x.negate();
+        }
+        Some(Self {
+            x,
+            y,
+            z: Field::ONE,
+            t: x.mul(&y)
+        })
+    }
+    fn encode(&self) -> [u8; 32] {
+        let z = self.z.invert();
+        let mut bytes = self.y.mul(&z).encode();
+        if self.x.mul(&z).is_negative() {
+            bytes[31] |= 0x80;
+        }
+        bytes
+    }
+    fn negate(&self) -> Self {
+        Self {
+            x: self.x.negate(),
+            y: self.y,
+            z: self.z,
+            t: self.t.negate()
+        }
+    }
+    /// The unified addition formula from RFC 8032 section 5.1.4, which also doubles
+    fn add(&self, other: &Self) -> Self {
+        let d2 = Field::d();
+        let d2 = d2.add(&d2);
+        let a = self.y.sub(&self.x).mul(&other.y.sub(&other.x));
+        let b = self.y.add(&self.x).mul(&other.y.add(&other.x));
+        let c = self.t.mul(&d2).mul(&other.t);
+        let d = self.z.add(&self.z).mul(&other.z);
+        let e = b.sub(&a);
+        let f = d.sub(&c);
+        let g = d.add(&c);
+        let h = b.add(&a);
+        Self {
+            x: e.mul(&f),
+            y: g.mul(&h),
+            z: f.mul(&g),
+            t: e.mul(&h)
+        }
+    }
+    /// Multiply by a little-endian scalar
+    fn multiply(&self, scalar: &[u8; 32]) -> Self {
+        let mut result = Self::IDENTITY;
+        for bit in (0..256).rev() {
+            result = result.add(&result);
+            if scalar[bit / 8] >> (bit % 8) & 1 != 0 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+    fn public_key(text: &str) -> [u8; PUBLIC_KEY_SIZE] {
+        let mut key = [0; PUBLIC_KEY_SIZE];
+        key.copy_from_slice(&hex(text));
+        key
+    }
+    fn signature(text: &str) -> [u8; SIGNATURE_SIZE] {
+        let mut signature = [0; SIGNATURE_SIZE];
+        signature.copy_from_slice(&hex(text));
+        signature
+    }
+
+    // RFC 8032 section 7.1
+    const TEST_1_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const TEST_1_SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+    const TEST_2_KEY: &str = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+    const TEST_2_SIGNATURE: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+    const TEST_3_KEY: &str = "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025";
+    const TEST_3_SIGNATURE: &str = "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a";
+
+    #[test]
+    fn rfc_8032() {
+        assert_eq!(verify(&public_key(TEST_1_KEY), b"", &signature(TEST_1_SIGNATURE)), Ok(()));
+        assert_eq!(verify(&public_key(TEST_2_KEY), &[0x72], &signature(TEST_2_SIGNATURE)), Ok(()));
+        assert_eq!(verify(&public_key(TEST_3_KEY), &[0xaf, 0x82], &signature(TEST_3_SIGNATURE)), Ok(()));
+    }
+    #[test]
+    fn reject_message() {
+        assert_eq!(verify(&public_key(TEST_2_KEY), &[0x73], &signature(TEST_2_SIGNATURE)), Err(Error::Mismatch));
+        assert_eq!(verify(&public_key(TEST_3_KEY), &[0xaf, 0x82, 0], &signature(TEST_3_SIGNATURE)), Err(Error::Mismatch));
+        assert_eq!(verify(&public_key(TEST_1_KEY), &[0x72], &signature(TEST_2_SIGNATURE)), Err(Error::Mismatch));
+    }
+    #[test]
+    fn reject_signature() {
+        // Flipping a bit of R or S
+        let mut flipped = signature(TEST_3_SIGNATURE);
+        flipped[0] ^= 1;
+        assert!(verify(&public_key(TEST_3_KEY), &[0xaf, 0x82], &flipped).is_err());
+        let mut flipped = signature(TEST_3_SIGNATURE);
+        flipped[40] ^= 1;
+        assert_eq!(verify(&public_key(TEST_3_KEY), &[0xaf, 0x82], &flipped), Err(Error::Mismatch));
+    }
+    #[test]
+    fn reject_non_canonical() {
+        // TEST 1 with L added to S, which satisfies the verification equation all the same
+        let mut malleated = signature(TEST_1_SIGNATURE);
+        malleated[32..].copy_from_slice(&hex("4c8c7872aa064e049dbb3013fbf29380d25bf5f0595bbe24655141438e7a101b"));
+        assert_eq!(verify(&public_key(TEST_1_KEY), b"", &malleated), Err(Error::Encoding));
+        let mut too_large = signature(TEST_1_SIGNATURE);
+        too_large[32..].copy_from_slice(&[0xFF; 32]);
+        assert_eq!(verify(&public_key(TEST_1_KEY), b"", &too_large), Err(Error::Encoding));
+    }
+}
@@ -0,0 +1,88 @@
+use bootinfo::EntropySource;
+use crate::uefi::{self, protocol::rng::Rng};
+
+/// Gather a 256-bit seed from the best source available: the firmware, RDSEED, RDRAND, then timestamp jitter
+pub fn seed(boot_services: &uefi::BootServices) -> ([u8; 32], EntropySource) {
+    let mut seed = [0; 32];
+    if let Ok(rng) = boot_services.locate_protocol::<Rng>() {
+        if rng.fill(&mut seed).is_ok() {
+            return (seed, EntropySource::Firmware)
+        }
+    }
+    // CPUID.(EAX=7, ECX=0):EBX[18] and CPUID.1:ECX[30]
+    if cpuid(0).0 >= 7 && cpuid(7).1 & 1 << 18 != 0 && fill(&mut seed, rdseed) {
+        return (seed, EntropySource::Rdseed)
+    }
+    if cpuid(1).2 & 1 << 30 != 0 && fill(&mut seed, rdrand) {
+        return (seed, EntropySource::Rdrand)
+    }
+    jitter(boot_services, &mut seed);
+    (seed, EntropySource::Timing)
+}
+
+/// Fill `seed` 8 bytes at a time, failing if the source runs dry
+fn fill(seed: &mut [u8; 32], source: fn() -> Option<u64>) -> bool {
+    for chunk in seed.chunks_exact_mut(8) {
+        match source() {
+            Some(value) => chunk.copy_from_slice(&value.to_le_bytes()),
+            None => return false
+        }
+    }
+    true
+}
+
+/// Fold the variation in how long short stalls take into the seed.
+/// Each sample only holds a bit or two of entropy, so many are taken.
+fn jitter(boot_services: &uefi::BootServices, seed: &mut [u8; 32]) {
+    const SAMPLES: usize = 2048;
+    let mut lanes = [0u64; 4];
+    let mut last = rdtsc();
+    for i in 0..SAMPLES {
+        let _ = boot_services.stall(1);
+        let now = rdtsc();
+        let lane = &mut lanes[i % lanes.len()];
+        *lane = (lane.rotate_left(7) ^ now.wrapping_sub(last)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        last = now;
+    }
+    for (lane, chunk) in lanes.iter().zip(seed.chunks_exact_mut(8)) {
+        chunk.copy_from_slice(&mix(*lane).to_le_bytes());
+    }
+}
+/// The SplitMix64 finaliser, so that every bit of a lane affects every bit of the output
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+/// EAX, EBX, ECX and EDX of CPUID leaf `leaf`, subleaf 0
+fn cpuid(leaf: u32) -> (u32, u32, u32, u32) {
+    let result = unsafe { core::arch::x86_64::__cpuid_count(leaf, 0) };
+    (result.eax, result.ebx, result.ecx, result.edx)
+}
+fn rdseed() -> Option<u64> {
+    // RDSEED fails more readily than RDRAND while the conditioner refills
+    for _ in 0..100 {
+        let (value, ok): (u64, u8);
+        unsafe { asm!("rdseed {}", "setc {}", out(reg) value, out(reg_byte) ok, options(nomem, nostack)) }
+        if ok != 0 {
+            return Some(value)
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+fn rdrand() -> Option<u64> {
+    // Intel recommends giving up after 10 consecutive failures
+    for _ in 0..10 {
+        let (value, ok): (u64, u8);
+        unsafe { asm!("rdrand {}", "setc {}", out(reg) value, out(reg_byte) ok, options(nomem, nostack)) }
+        if ok != 0 {
+            return Some(value)
+        }
+    }
+    None
+}
+fn rdtsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
@@ -0,0 +1,209 @@
+use bootinfo::{BootInfo, Framebuffer, Processor, Slice};
+use kalloc::{Allocator, VirtualAddress, page};
+use crate::{loader::{self, Kernel}, memory::{self, MemoryMap}, panic, uefi::{self, RuntimeServices, mem, protocol::mp::MpServices}};
+
+/// Everything needed to enter the kernel, prepared while boot services are still available
+pub struct Handoff {
+    kernel: Kernel,
+    /// Top of the kernel stack, 16-byte aligned
+    stack: u64,
+    free: &'static mut page::Table<page::Level4Entry>,
+    boot_info: &'static mut BootInfo,
+    memory_map: MemoryMap
+}
+impl Handoff {
+    /// Size of the initial kernel stack in pages
+    pub const STACK_PAGES: usize = 16;
+    /// Runtime services are moved to their physical address plus this offset before entering the kernel
+    pub const RUNTIME_OFFSET: u64 = 0xFFFF_FE00_0000_0000;
+
+    /// Allocate the kernel stack and allocator pages, then identity map physical memory into the kernel's address space.
+    /// Memory allocated with boot services after this point will not be identity mapped.
+    pub fn prepare(boot_services: &'static uefi::BootServices, kernel: Kernel) -> Result<Self, loader::Error> {
+        let boot_info = loader::allocate_zeroed(boot_services, mem::MemoryType::BOOT_INFO) as *mut BootInfo;
+        if boot_info.is_null() {
+            return Err(uefi::Error::OutOfResources.into())
+        }
+        let boot_info = unsafe {
+            boot_info.write(BootInfo::new());
+            &mut *boot_info
+        };
+        boot_info.kernel_slide = kernel.slide;
+
+        let stack = boot_services.allocate_pages(mem::MemoryType::KERNEL, Self::STACK_PAGES)?;
+        let stack = stack as u64 + (Self::STACK_PAGES * 0x1000) as u64;
+
+        // The allocator requires a free page table with a page already mapped at address zero
+        let free = loader::allocate_zeroed(boot_services, mem::MemoryType::ALLOCATOR);
+        if free.is_null() {
+            return Err(uefi::Error::OutOfResources.into())
+        }
+        let free = unsafe { &mut *(free as *mut page::Table<page::Level4Entry>) };
+        let first_free = loader::allocate_zeroed(boot_services, mem::MemoryType::ALLOCATOR);
+        if first_free.is_null() {
+            return Err(uefi::Error::OutOfResources.into())
+        }
+        unsafe { free.map(VirtualAddress::NULL, first_free, || loader::allocate_zeroed(boot_services, mem::MemoryType::ALLOCATOR)) }
+            .ok_or(loader::Error::Mapping)?;
+
+        let memory_map = boot_services.get_memory_map()?;
+        let segments = MemoryMap::reserve(boot_services, memory_map.iter().count())?;
+        for descriptor in memory_map.iter() {
+            identity_map(boot_services, kernel.page_table, descriptor.physcial_start, descriptor.pages)?;
+            if descriptor.attributes.contains(mem::MemoryAttributes::RUNTIME) {
+                map_range(boot_services, kernel.page_table, descriptor.physcial_start + Self::RUNTIME_OFFSET, descriptor.physcial_start, descriptor.pages)?;
+            }
+        }
+
+        Ok(Self {
+            kernel,
+            stack,
+            free,
+            boot_info,
+            memory_map: segments
+        })
+    }
+    /// The boot info given to the kernel, which may be filled in until `Handoff::enter`
+    #[inline]
+    pub fn boot_info(&mut self) -> &mut BootInfo {
+        self.boot_info
+    }
+    /// Record the ACPI and SMBIOS tables installed by the firmware
+    pub fn set_firmware_tables(&mut self, system_table: &uefi::SystemTable) {
+        let table = |guid: &uefi::Guid| system_table.find_table(guid).map_or(0, |table| table as u64);
+        // Prefer the ACPI 2.0 RSDP as it also has the XSDT address
+        self.boot_info.rsdp = match table(&uefi::ConfigurationTable::ACPI_20_GUID) {
+            0 => table(&uefi::ConfigurationTable::ACPI_GUID),
+            rsdp => rsdp
+        };
+        self.boot_info.smbios = table(&uefi::ConfigurationTable::SMBIOS_GUID);
+        self.boot_info.smbios3 = table(&uefi::ConfigurationTable::SMBIOS3_GUID);
+    }
+    /// Give the kernel a framebuffer, identity mapping it if the firmware memory map did not cover it
+    pub fn set_framebuffer(&mut self, boot_services: &uefi::BootServices, framebuffer: Framebuffer) -> Result<(), loader::Error> {
+        identity_map(boot_services, self.kernel.page_table, framebuffer.base, framebuffer.size.div_ceil(mem::PAGE_SIZE as u64))?;
+        self.boot_info.framebuffer = framebuffer;
+        Ok(())
+    }
+    /// Copy the kernel command line into memory the kernel keeps until it has read the boot info
+    pub fn set_command_line(&mut self, boot_services: &uefi::BootServices, command_line: &str) -> Result<(), loader::Error> {
+        self.boot_info.command_line = copy(boot_services, self.kernel.page_table, command_line.as_bytes())?.into();
+        Ok(())
+    }
+    /// Describe every processor to the kernel in memory it keeps until it has read the boot info, returning how many there are and how many are enabled.
+    /// On failure the kernel is told of no processors.
+    pub fn set_processors(&mut self, boot_services: &uefi::BootServices, mp_services: &mut MpServices) -> Result<(usize, usize), loader::Error> {
+        let (count, enabled) = mp_services.count()?;
+        if count == 0 {
+            return Ok((0, 0))
+        }
+        let memory = allocate(boot_services, self.kernel.page_table, count * core::mem::size_of::<Processor>())? as *mut Processor;
+        for i in 0..count {
+            let info = mp_services.info(i)?;
+            unsafe { memory.add(i).write((&info).into()) }
+        }
+        self.boot_info.processors = Slice { address: memory, len: count };
+        Ok((count, enabled))
+    }
+    /// Record the final firmware memory map for the kernel and its allocator
+    pub fn set_memory_map(&mut self, memory_map: &mem::FinalMemoryMap) -> Result<(), memory::Error> {
+        self.memory_map.convert(memory_map)?;
+        self.boot_info.memory_map = self.memory_map.segments().into();
+        Ok(())
+    }
+    /// Move runtime services to their higher half mapping so that the kernel can keep calling them.
+    /// If the firmware refuses, runtime services stay at their identity mapped physical addresses.
+    pub fn set_runtime_services(&mut self, runtime_services: &'static RuntimeServices, memory_map: &mut mem::FinalMemoryMap) {
+        for descriptor in memory_map.iter_mut() {
+            if descriptor.attributes.contains(mem::MemoryAttributes::RUNTIME) {
+                descriptor.virtual_start = descriptor.physcial_start + Self::RUNTIME_OFFSET;
+            }
+        }
+        let address = runtime_services as *const RuntimeServices as u64;
+        self.boot_info.runtime_services = match unsafe { runtime_services.set_virtual_address_map(memory_map) } {
+            Ok(()) => {
+                panic::disable_reset();
+                address + Self::RUNTIME_OFFSET
+            },
+            Err(_) => address
+        };
+    }
+    /// Build the allocator from the memory map, switch to the kernel's address space and stack and call its entry point
+    /// # Safety
+    /// Boot services must have exited and the memory map must have been set
+    pub unsafe fn enter(self) -> ! {
+        let mut allocator = Allocator::new(self.free);
+        allocator.discover_pages(self.memory_map.segments().iter().copied());
+
+        // Enable the no-execute bit in EFER so that non-executable segments can be mapped
+        asm! {
+            "rdmsr",
+            "or eax, 0x800",
+            "wrmsr",
+            in("ecx") 0xC0000080u32,
+            out("eax") _,
+            out("edx") _
+        }
+        asm! {
+            "mov cr3, {page_table}",
+            "mov rsp, {stack}",
+            "xor ebp, ebp",
+            "call {trampoline}",
+            page_table = in(reg) self.kernel.page_table as *mut page::Table<page::Level4Entry>,
+            stack = in(reg) self.stack,
+            trampoline = in(reg) trampoline as extern "sysv64" fn(u64, *const BootInfo, *const Allocator) -> !,
+            in("rdi") *self.kernel.entry,
+            in("rsi") self.boot_info as *const BootInfo,
+            in("rdx") &allocator as *const Allocator,
+            options(noreturn)
+        }
+    }
+}
+
+/// Runs on the kernel stack so that the boot info and allocator are passed according to the kernel's calling convention.
+/// The kernel is compiled for a SysV target while the bootloader's `extern "C"` is the Microsoft x64 ABI.
+extern "sysv64" fn trampoline(entry: u64, boot_info: *const BootInfo, allocator: *const Allocator) -> ! {
+    unsafe {
+        let entry: extern "sysv64" fn(*const BootInfo, Allocator) -> ! = core::mem::transmute(entry);
+        entry(boot_info, core::ptr::read(allocator))
+    }
+}
+
+/// Map physical pages to the same virtual address, writable, skipping any already mapped
+fn identity_map(boot_services: &uefi::BootServices, page_table: &mut page::Table<page::Level4Entry>, start: u64, pages: u64) -> Result<(), loader::Error> {
+    map_range(boot_services, page_table, start, start, pages)
+}
+/// Map physical pages at `virtual_start`, writable, skipping any already mapped
+fn map_range(boot_services: &uefi::BootServices, page_table: &mut page::Table<page::Level4Entry>, virtual_start: u64, physical_start: u64, pages: u64) -> Result<(), loader::Error> {
+    for i in 0..pages {
+        let address = virtual_start + i * 0x1000;
+        if let Some(entry) = unsafe { page_table.page_entry(address.into()) } {
+            if entry.present() {
+                continue
+            }
+        }
+        let page = (physical_start + i * 0x1000) as _;
+        let entry = unsafe { page_table.map(address.into(), page, || loader::allocate_table(boot_services)) }
+            .ok_or(loader::Error::Mapping)?;
+        entry.set_write();
+    }
+    Ok(())
+}
+/// Allocate identity mapped `BOOT_INFO` pages, which the kernel may reclaim once it has read the boot info
+fn allocate(boot_services: &uefi::BootServices, page_table: &mut page::Table<page::Level4Entry>, size: usize) -> Result<*mut u8, loader::Error> {
+    let pages = size.div_ceil(mem::PAGE_SIZE);
+    let memory = boot_services.allocate_pages(mem::MemoryType::BOOT_INFO, pages)? as *mut u8;
+    identity_map(boot_services, page_table, memory as u64, pages as u64)?;
+    Ok(memory)
+}
+/// Copy data into `BOOT_INFO` pages
+fn copy(boot_services: &uefi::BootServices, page_table: &mut page::Table<page::Level4Entry>, data: &[u8]) -> Result<&'static [u8], loader::Error> {
+    if data.is_empty() {
+        return Ok(&[])
+    }
+    let memory = allocate(boot_services, page_table, data.len())?;
+    unsafe {
+        core::ptr::copy_nonoverlapping(data.as_ptr(), memory, data.len());
+        Ok(core::slice::from_raw_parts(memory, data.len()))
+    }
+}
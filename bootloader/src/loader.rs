@@ -0,0 +1,226 @@
+use kalloc::{Page, VirtualAddress, page};
+use crate::{crypto::ed25519, uefi::{self, mem, protocol::{self, device, file, image}}};
+
+/// Read a file from the volume the bootloader was loaded from into pages of `memory_type`
+pub fn load_file(boot_services: &'static uefi::BootServices, image_handle: uefi::ImageHandle, path: &str, memory_type: mem::MemoryType) -> Result<&'static mut [u8], uefi::Error> {
+    let loaded_image = boot_services.open::<image::LoadedImage>(image_handle.into(), image_handle)?;
+    let mut file_system = boot_services.open::<file::SimpleFileSystem>(loaded_image.device, image_handle)?;
+
+    let root = file_system.open_volume()?;
+    let file = root.open(path, file::Mode::READ);
+    let _ = root.close();
+    let file = file?;
+
+    let data = read_file(boot_services, file, memory_type);
+    let _ = file.close();
+    data
+}
+
+/// Read the detached signature stored beside the file at `path` as `<path>.sig`
+pub fn load_signature(boot_services: &'static uefi::BootServices, image_handle: uefi::ImageHandle, path: &str) -> Result<[u8; ed25519::SIGNATURE_SIZE], uefi::Error> {
+    const SUFFIX: &str = ".sig";
+    let mut buffer = [0; file::File::MAX_PATH];
+    let len = path.len() + SUFFIX.len();
+    if len > buffer.len() {
+        return Err(uefi::Error::BadBufferSize)
+    }
+    buffer[..path.len()].copy_from_slice(path.as_bytes());
+    buffer[path.len()..len].copy_from_slice(SUFFIX.as_bytes());
+    // Safe: both halves are valid UTF-8 and the join is between characters
+    let signature_path = unsafe { core::str::from_utf8_unchecked(&buffer[..len]) };
+
+    let data = load_file(boot_services, image_handle, signature_path, mem::MemoryType::LOADER_DATA)?;
+    let mut signature = [0; ed25519::SIGNATURE_SIZE];
+    let result = if data.len() == signature.len() {
+        signature.copy_from_slice(data);
+        Ok(signature)
+    } else {
+        Err(uefi::Error::CompromisedData)
+    };
+    let _ = boot_services.free_pages(data.as_mut_ptr() as *mut Page, data.len().div_ceil(mem::PAGE_SIZE));
+    result
+}
+
+/// The device path of the volume the bootloader was loaded from
+pub fn boot_device(boot_services: &'static uefi::BootServices, image_handle: uefi::ImageHandle) -> Result<protocol::Opened<device::Path>, uefi::Error> {
+    let loaded_image = boot_services.open::<image::LoadedImage>(image_handle.into(), image_handle)?;
+    boot_services.open::<device::Path>(loaded_image.device, image_handle)
+}
+
+fn read_file(boot_services: &uefi::BootServices, file: &mut file::File, memory_type: mem::MemoryType) -> Result<&'static mut [u8], uefi::Error> {
+    let info = file.info()?;
+    if info.attributes.directory() {
+        return Err(uefi::Error::NotFound)
+    }
+    let size = info.file_size as usize;
+    let pages = size.div_ceil(mem::PAGE_SIZE);
+
+    let memory = boot_services.allocate_pages(memory_type, pages)?;
+    // Safe: the pages were just allocated for our exclusive use
+    let data = unsafe { core::slice::from_raw_parts_mut(memory as *mut u8, size) };
+
+    let mut read = 0;
+    while read < size {
+        match file.read(&mut data[read..]) {
+            Ok(0) => {
+                let _ = boot_services.free_pages(memory, pages);
+                return Err(uefi::Error::EndOfFile)
+            },
+            Ok(bytes) => read += bytes,
+            Err(error) => {
+                let _ = boot_services.free_pages(memory, pages);
+                return Err(error)
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Error {
+    Elf(elf64::Error),
+    Uefi(uefi::Error),
+    /// Page tables could not be allocated or two segments share a page
+    Mapping
+}
+impl From<elf64::Error> for Error {
+    fn from(error: elf64::Error) -> Self {
+        Self::Elf(error)
+    }
+}
+impl From<uefi::Error> for Error {
+    fn from(error: uefi::Error) -> Self {
+        Self::Uefi(error)
+    }
+}
+impl From<Error> for uefi::Status {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Elf(_) => Self::LOAD_ERROR,
+            Error::Uefi(error) => error.into(),
+            Error::Mapping => Self::OUT_OF_RESOURCES
+        }
+    }
+}
+
+/// A kernel mapped into its own address space
+pub struct Kernel {
+    pub entry: VirtualAddress,
+    pub page_table: &'static mut page::Table<page::Level4Entry>,
+    /// How far the kernel was moved from its link address
+    pub slide: u64
+}
+
+/// Slides are multiples of 2 MiB so that the kernel could be mapped with large pages
+const SLIDE_ALIGN: u64 = 2 << 20;
+/// Keeps a kernel linked at -2 GiB within the top 2 GiB required by the kernel code model
+const MAX_SLIDE: u64 = 1 << 30;
+
+/// Copy each loadable segment of the kernel ELF into `KERNEL` pages and map them at their link address.
+/// If `random` is given and the kernel is position-independent it is instead moved by a random slide.
+pub fn load_kernel(boot_services: &uefi::BootServices, image: &[u8], random: Option<&[u8; 32]>) -> Result<Kernel, Error> {
+    let elf = elf64::Elf::parse(image)?;
+    let slide = match random {
+        Some(random) if elf.relocatable() => {
+            let end = elf.segments()
+                .try_fold(0, |end: u64, segment| segment.virtual_address.checked_add(segment.memory_size).map(|segment_end| end.max(segment_end)))
+                .ok_or(elf64::Error::Segment)?;
+            let slots = (MAX_SLIDE / SLIDE_ALIGN).min((u64::MAX - end) / SLIDE_ALIGN + 1);
+            let random = u64::from_le_bytes([random[0], random[1], random[2], random[3], random[4], random[5], random[6], random[7]]);
+            random % slots * SLIDE_ALIGN
+        },
+        _ => 0
+    };
+
+    let page_table = allocate_table(boot_services);
+    if page_table.is_null() {
+        return Err(uefi::Error::OutOfResources.into())
+    }
+    // Safe: freshly allocated and zeroed
+    let page_table = unsafe { &mut *(page_table as *mut page::Table<page::Level4Entry>) };
+
+    for segment in elf.segments() {
+        map_segment(boot_services, page_table, &segment, elf.segment_data(&segment), slide)?;
+    }
+    if elf.relocatable() {
+        relocate(page_table, &elf, slide)?;
+    }
+
+    // The slide keeps every segment below the top of the address space, so an entry point that overflows is outside all of them
+    let entry = elf.entry().checked_add(slide).ok_or(elf64::Error::Segment)?;
+    Ok(Kernel {
+        entry: entry.into(),
+        page_table,
+        slide
+    })
+}
+
+fn map_segment(boot_services: &uefi::BootServices, page_table: &mut page::Table<page::Level4Entry>, segment: &elf64::ProgramHeader, data: &[u8], slide: u64) -> Result<(), Error> {
+    let virtual_address = segment.virtual_address.checked_add(slide).ok_or(elf64::Error::Segment)?;
+    let start = virtual_address & !0xFFF;
+    let end = virtual_address.checked_add(segment.memory_size)
+        .and_then(|end| end.checked_add(0xFFF))
+        .ok_or(elf64::Error::Segment)? & !0xFFF;
+    let pages = ((end - start) / 0x1000) as usize;
+    if pages == 0 {
+        return Ok(())
+    }
+
+    let memory = boot_services.allocate_pages(mem::MemoryType::KERNEL, pages)?;
+    unsafe {
+        // Zeroing everything first takes care of the BSS and any padding around the file data
+        core::ptr::write_bytes(memory, 0, pages);
+        let offset = (virtual_address - start) as usize;
+        core::ptr::copy_nonoverlapping(data.as_ptr(), (memory as *mut u8).add(offset), data.len());
+
+        for i in 0..pages {
+            let address = VirtualAddress::from(start + (i * 0x1000) as u64);
+            let entry = page_table.map(address, memory.add(i), || allocate_table(boot_services)).ok_or(Error::Mapping)?;
+            if segment.writable() {
+                entry.set_write();
+            }
+            if !segment.executable() {
+                entry.set_no_execute();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply the `R_X86_64_RELATIVE` relocations of a position-independent kernel loaded `slide` bytes from its link address.
+/// Writes go through the physical addresses so that read-only segments can be relocated too.
+fn relocate(page_table: &page::Table<page::Level4Entry>, elf: &elf64::Elf, slide: u64) -> Result<(), Error> {
+    for rela in elf.relocations()? {
+        let rela = rela?;
+        if rela.relocation_type() != elf64::Rela::X86_64_RELATIVE {
+            return Err(elf64::Error::Relocation.into())
+        }
+        let target = rela.offset.checked_add(slide).ok_or(elf64::Error::Relocation)?;
+        let value = (rela.addend as u64).checked_add(slide).ok_or(elf64::Error::Relocation)?;
+        // The target may straddle two pages that are not physically contiguous
+        for (i, byte) in value.to_le_bytes().iter().enumerate() {
+            let address = target.checked_add(i as u64).ok_or(elf64::Error::Relocation)?;
+            let physical = unsafe { page_table.physical::<u8>(address.into()) };
+            if physical.is_null() {
+                return Err(elf64::Error::Relocation.into())
+            }
+            unsafe { *physical = *byte };
+        }
+    }
+    Ok(())
+}
+
+/// Allocate a zeroed page for use as a page table, or null if out of memory
+pub fn allocate_table(boot_services: &uefi::BootServices) -> *mut Page {
+    allocate_zeroed(boot_services, mem::MemoryType::PAGE_TABLE)
+}
+/// Allocate a single zeroed page, or null if out of memory
+pub fn allocate_zeroed(boot_services: &uefi::BootServices, memory_type: mem::MemoryType) -> *mut Page {
+    match boot_services.allocate_pages(memory_type, 1) {
+        Ok(page) => {
+            unsafe { core::ptr::write_bytes(page, 0, 1) };
+            page
+        },
+        Err(_) => core::ptr::null_mut()
+    }
+}
@@ -0,0 +1,83 @@
+use core::fmt::{self, Write};
+use serial::Serial;
+use crate::uefi::{self, protocol::serial::SerialIo};
+
+/// How important a message is, from most to least
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace
+}
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE"
+        }
+    }
+}
+
+/// Messages less important than this are dropped
+static mut LEVEL: Level = Level::Info;
+/// Also write messages to the firmware console
+static mut MIRROR: bool = false;
+/// The firmware's serial port while boot services are available
+static mut SERIAL_IO: *mut SerialIo = core::ptr::null_mut();
+/// The UART, programmed on first use once the firmware no longer owns it
+static mut RAW: Option<Serial> = None;
+
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => ($crate::log::log($level, format_args!($($arg)*)));
+}
+macro_rules! error {
+    ($($arg:tt)*) => (log!($crate::log::Level::Error, $($arg)*));
+}
+macro_rules! warn {
+    ($($arg:tt)*) => (log!($crate::log::Level::Warn, $($arg)*));
+}
+macro_rules! info {
+    ($($arg:tt)*) => (log!($crate::log::Level::Info, $($arg)*));
+}
+macro_rules! debug {
+    ($($arg:tt)*) => (log!($crate::log::Level::Debug, $($arg)*));
+}
+
+/// Log through the firmware's serial port if it has one, otherwise straight to COM1
+pub fn init(boot_services: &uefi::BootServices, level: Level, mirror: bool) {
+    unsafe {
+        LEVEL = level;
+        MIRROR = mirror;
+        SERIAL_IO = boot_services.locate_protocol::<SerialIo>().map_or(core::ptr::null_mut(), |serial| serial as *mut SerialIo);
+    }
+}
+/// Stop using the firmware's serial port, which is gone once boot services exit
+pub fn exit() {
+    unsafe { SERIAL_IO = core::ptr::null_mut() }
+}
+
+#[doc(hidden)]
+pub fn log(level: Level, args: fmt::Arguments) {
+    unsafe {
+        if level > LEVEL {
+            return
+        }
+        let _ = match SERIAL_IO.as_mut() {
+            Some(serial) => writeln!(serial, "[{}] {}", level.name(), args),
+            None => {
+                // Only ever touched from this single threaded logger, so no other reference to RAW can exist
+                let raw = &mut *core::ptr::addr_of_mut!(RAW);
+                writeln!(raw.get_or_insert_with(|| Serial::new(Serial::COM1)), "[{}] {}", level.name(), args)
+            }
+        };
+        // The console is already gone after boot services exit, so this is a no-op then
+        if MIRROR {
+            println!("[{}] {}", level.name(), args);
+        }
+    }
+}
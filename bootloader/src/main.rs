@@ -1,40 +1,135 @@
-#![no_std]
-#![no_main]
+// Unit tests run on the host with the standard library's test harness
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 
 #![feature(asm)]
 #![feature(start)]
 #![feature(abi_efiapi)]
 
-#[panic_handler]
-fn panic(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
-}
-
+#[macro_use]
+mod console;
+#[macro_use]
+mod log;
+mod panic;
 mod uefi;
+mod loader;
+mod memory;
+mod handoff;
+mod video;
+mod menu;
+mod config;
+mod entropy;
+mod crypto;
+
+use crypto::ed25519;
+
+/// Longest command line that can be entered in the boot menu
+const COMMAND_LINE_MAX: usize = 512;
+/// Kernels must be signed with the private half of this key.
+/// The build script embeds `keys/kernel.pub`, which `run` creates on first use, or the key at `CHERIMOYA_PUBLIC_KEY`.
+const PUBLIC_KEY: &[u8; ed25519::PUBLIC_KEY_SIZE] = include_bytes!(concat!(env!("OUT_DIR"), "/kernel.pub"));
 
 #[no_mangle]
-extern "efiapi" fn uefi_start<'a>(handle: uefi::ImageHandle, system_table: &'static mut uefi::SystemTable) -> ! {
-    let uefi_memory_map = system_table.boot_services.get_memory_map().unwrap();
+extern "efiapi" fn uefi_start(handle: uefi::ImageHandle, system_table: &'static mut uefi::SystemTable) -> uefi::Status {
+    // Only failures before boot services exit are reported back to the firmware
+    match boot(handle, system_table) {
+        Ok(never) => match never {},
+        Err(status) => {
+            println!("Unable to boot: {}", status);
+            status
+        }
+    }
+}
 
+fn boot(handle: uefi::ImageHandle, system_table: &'static mut uefi::SystemTable) -> Result<core::convert::Infallible, uefi::Status> {
+    let boot_services = system_table.boot_services;
+    let runtime_services = system_table.runtime_services;
+    console::init(system_table);
+    // Until the configuration says otherwise
+    panic::init(runtime_services, config::DEFAULT_PANIC_ACTION);
 
+    let config = match loader::load_file(boot_services, handle, config::PATH, uefi::mem::MemoryType::LOADER_DATA) {
+        Ok(file) => config::Config::parse(file).map_err(|error| {
+            println!("{}: {}", config::PATH, error);
+            uefi::Status::LOAD_ERROR
+        })?,
+        Err(uefi::Error::NotFound) => config::Config::fallback(),
+        Err(error) => return Err(error.into())
+    };
+    log::init(boot_services, config.verbosity, config.mirror);
+    panic::set_action(config.panic);
+    if let Ok(device) = loader::boot_device(boot_services, handle) {
+        info!("Booting from {}", &*device);
+    }
+    let mut command_line = [0; COMMAND_LINE_MAX];
+    let choice = menu::run(config.entries(), config.default, config.timeout, &mut command_line)?;
 
-    let msg = &[0xFF, 0xFF, 0xFF, 0xFF, core::mem::size_of::<Option<kalloc::Page>>() as u8 + b'0', 0,0,0, 0xFF, 0xFF, 0xFF, 0xFF];
-    system_table.stdout.print_utf16(msg.as_ptr() as _);
+    // Gathered before the kernel is loaded so that it can also place the kernel
+    let (seed, seed_source) = entropy::seed(boot_services);
+    debug!("Seeded from {:?}", seed_source);
 
-    system_table.boot_services.exit_boot_services(handle, &uefi_memory_map);
+    let kernel_image = loader::load_file(boot_services, handle, choice.entry.kernel, uefi::mem::MemoryType::LOADER_DATA)?;
+    // Nothing in an unsigned or tampered kernel is trusted, not even its headers
+    match loader::load_signature(boot_services, handle, choice.entry.kernel) {
+        Ok(signature) => if let Err(error) = ed25519::verify(PUBLIC_KEY, kernel_image, &signature) {
+            error!("{} was rejected: {}", choice.entry.kernel, error);
+            menu::alert(format_args!("{} was rejected: {}", choice.entry.kernel, error));
+            return Err(uefi::Status::SECURITY_VIOLATION)
+        },
+        Err(error) => {
+            error!("{} has no usable signature: {}", choice.entry.kernel, error);
+            menu::alert(format_args!("{} has no usable signature: {}", choice.entry.kernel, error));
+            return Err(uefi::Status::SECURITY_VIOLATION)
+        }
+    }
+    info!("Verified the signature of {}", choice.entry.kernel);
+    // Separate from the kernel's seed so that learning the slide reveals nothing about it
+    let slide_seed = if config.kaslr { Some(entropy::seed(boot_services).0) } else { None };
+    let kernel = loader::load_kernel(boot_services, kernel_image, slide_seed.as_ref())?;
+    debug!("Kernel slide is {:#x}", kernel.slide);
+    // Loaded before the handoff is prepared so that it is identity mapped along with the rest of memory
+    let initrd = match choice.entry.initrd {
+        Some(path) => loader::load_file(boot_services, handle, path, uefi::mem::MemoryType::INITRD)?,
+        None => &mut []
+    };
+    let mut handoff = handoff::Handoff::prepare(boot_services, kernel)?;
+    handoff.set_command_line(boot_services, choice.command_line)?;
+    handoff.boot_info().initrd = (&*initrd).into();
+    handoff.boot_info().seed = seed;
+    handoff.boot_info().seed_source = seed_source;
+    if let Ok((time, _)) = runtime_services.time() {
+        handoff.boot_info().boot_time = (&time).into();
+    }
+    handoff.set_firmware_tables(system_table);
+    // Without MP services the kernel has to find the other processors in the ACPI MADT
+    if let Ok(mp_services) = boot_services.locate_protocol::<uefi::protocol::mp::MpServices>() {
+        match handoff.set_processors(boot_services, mp_services) {
+            Ok((count, enabled)) => info!("Found {} processors, {} enabled", count, enabled),
+            Err(error) => warn!("Cannot describe the processors, the kernel will have to find them in the MADT: {:?}", error)
+        }
+    }
+    // Headless machines have no graphics output, the kernel can do without a framebuffer
+    if let Ok(framebuffer) = video::init(boot_services, config.video) {
+        handoff.set_framebuffer(boot_services, framebuffer)?;
+    }
+
+    let uefi_memory_map = boot_services.get_memory_map()?;
+    info!("Memory map has {} descriptors, exiting boot services", uefi_memory_map.total_size / uefi_memory_map.descriptor_size);
+    console::exit();
+    log::exit();
+    let mut uefi_memory_map = boot_services.exit_boot_services(handle, uefi_memory_map)?;
 
-    let map_count = uefi_memory_map.total_size / uefi_memory_map.descriptor_size;
-    // 2 extra pages for the kernel 
-    const EXTRA_PAGES: usize = 2 + 1;
-    let required_pages = (core::mem::size_of::<kalloc::Page>() * (map_count + EXTRA_PAGES)) / 0x1000;
-    uefi_memory_map.iter().find(|d| d.pages as usize >= required_pages );
-    for descriptor in uefi_memory_map.iter() {
-        
+    // There is no console or firmware left to return to, only the serial log
+    if let Err(error) = handoff.set_memory_map(&uefi_memory_map) {
+        error!("Cannot convert the firmware memory map: {:?}", error);
+        panic::halt()
     }
-    loop {}
+    handoff.set_runtime_services(runtime_services, &mut uefi_memory_map);
+    info!("Entering the kernel");
+    unsafe { handoff.enter() }
 }
 
 #[allow(non_camel_case_types)]
 pub struct void {
     _opaque: [u8; 0]
-}
\ No newline at end of file
+}
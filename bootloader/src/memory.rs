@@ -0,0 +1,71 @@
+use kalloc::MemorySegment;
+use crate::uefi::{self, mem};
+
+#[derive(Copy, Clone, Debug)]
+pub enum Error {
+    /// The firmware memory map grew beyond the space reserved for it
+    Capacity,
+    /// Two firmware memory descriptors describe the same page
+    Overlap
+}
+
+/// The kernel's view of physical memory.
+/// Space is reserved while boot services are available and filled in from the final firmware memory map.
+pub struct MemoryMap {
+    segments: &'static mut [MemorySegment],
+    len: usize
+}
+impl MemoryMap {
+    /// Extra room for descriptors the firmware may add between now and exiting boot services
+    const SLACK: usize = 16;
+
+    /// Reserve space for the segments of a firmware memory map with `descriptors` entries
+    pub fn reserve(boot_services: &uefi::BootServices, descriptors: usize) -> Result<Self, uefi::Error> {
+        let capacity = descriptors + Self::SLACK;
+        let pages = (capacity * core::mem::size_of::<MemorySegment>()).div_ceil(mem::PAGE_SIZE);
+        let memory = boot_services.allocate_pages(mem::MemoryType::BOOT_INFO, pages)?;
+        // Safe: the pages were just allocated and MemorySegment is plain old data
+        let segments = unsafe { core::slice::from_raw_parts_mut(memory as *mut MemorySegment, pages * mem::PAGE_SIZE / core::mem::size_of::<MemorySegment>()) };
+        Ok(Self {
+            segments,
+            len: 0
+        })
+    }
+    /// Convert the firmware memory map, sorting by address and merging adjacent segments with the same usage and properties
+    pub fn convert(&mut self, memory_map: &mem::FinalMemoryMap) -> Result<(), Error> {
+        self.len = 0;
+        for descriptor in memory_map.iter() {
+            if descriptor.pages == 0 {
+                continue
+            }
+            *self.segments.get_mut(self.len).ok_or(Error::Capacity)? = descriptor.segment();
+            self.len += 1;
+        }
+
+        let segments = &mut self.segments[..self.len];
+        segments.sort_unstable_by_key(|segment| segment.page as u64);
+
+        let mut merged = 0;
+        for i in 0..segments.len() {
+            let segment = segments[i];
+            if i > 0 {
+                let previous = &mut segments[merged - 1];
+                if (segment.page as u64) < previous.end() {
+                    return Err(Error::Overlap)
+                }
+                if segment.page as u64 == previous.end() && segment.usage == previous.usage && segment.properties == previous.properties {
+                    previous.count += segment.count;
+                    continue
+                }
+            }
+            segments[merged] = segment;
+            merged += 1;
+        }
+        self.len = merged;
+        Ok(())
+    }
+    #[inline]
+    pub fn segments(&self) -> &[MemorySegment] {
+        &self.segments[..self.len]
+    }
+}
@@ -0,0 +1,173 @@
+use core::fmt::{self, Write};
+use crate::{console, uefi::{self, protocol::console::{Attribute, Keystroke, Output, ScanCode}}};
+
+/// A kernel the menu offers to boot
+#[derive(Copy, Clone)]
+pub struct Entry<'a> {
+    pub name: &'a str,
+    /// Path of the kernel image on the boot volume
+    pub kernel: &'a str,
+    /// Path of the initial ramdisk on the boot volume
+    pub initrd: Option<&'a str>,
+    pub command_line: &'a str
+}
+
+/// The entry to boot and the command line to give it, which may have been edited
+pub struct Choice<'a> {
+    pub entry: &'a Entry<'a>,
+    pub command_line: &'a str
+}
+
+const ONE_SECOND: usize = 1_000_000;
+/// Microseconds between checks for a key press while counting down
+const POLL: usize = 10_000;
+/// The firmware's watchdog timeout, restored once a choice is made
+const WATCHDOG: usize = 5 * 60;
+const HIGHLIGHT: Attribute = Attribute::new(Attribute::BLACK, Attribute::LIGHT_GRAY);
+const ALERT: Attribute = Attribute::new(Attribute::WHITE, Attribute::RED);
+/// Rows above the first entry
+const HEADER: usize = 2;
+/// Rows below the last entry before the countdown, which the command line editor reuses
+const FOOTER: usize = 3;
+
+/// Let the user pick an entry and edit its command line, booting `default` once `timeout` seconds pass without a key press.
+/// With no timeout the menu waits forever, and a timeout of zero skips the menu entirely.
+/// An edited command line is stored in `buffer`.
+pub fn run<'a>(entries: &'a [Entry<'a>], default: usize, timeout: Option<u32>, buffer: &'a mut [u8]) -> Result<Choice<'a>, uefi::Error> {
+    let entry = entries.get(default).ok_or(uefi::Error::NotFound)?;
+    if timeout == Some(0) {
+        return Ok(Choice {
+            entry,
+            command_line: entry.command_line
+        })
+    }
+    let system_table = unsafe { console::system_table() }.ok_or(uefi::Error::Unsupported)?;
+    let boot_services = system_table.boot_services;
+
+    // Nobody should be reset for taking their time to choose
+    let _ = boot_services.set_watchdog(0);
+    let result = select(entries, default, timeout, buffer);
+    let _ = boot_services.set_watchdog(WATCHDOG);
+
+    let output = &mut *system_table.stdout;
+    let _ = output.set_attribute(Attribute::DEFAULT);
+    let _ = output.clear_screen();
+    let _ = output.enable_cursor(true);
+
+    let (selected, edited) = result?;
+    let entry = &entries[selected];
+    let command_line = match edited {
+        Some(len) => core::str::from_utf8(&buffer[..len]).map_err(|_| uefi::Error::InvalidParameter)?,
+        None => entry.command_line
+    };
+    Ok(Choice {
+        entry,
+        command_line
+    })
+}
+
+/// Run the menu until an entry is chosen, returning its index and the length of the command line if it was edited
+fn select(entries: &[Entry], mut selected: usize, mut remaining: Option<u32>, buffer: &mut [u8]) -> Result<(usize, Option<usize>), uefi::Error> {
+    let system_table = unsafe { console::system_table() }.ok_or(uefi::Error::Unsupported)?;
+    let boot_services = system_table.boot_services;
+    let countdown_row = HEADER + entries.len() + FOOTER;
+
+    draw(system_table.stdout, entries, selected)?;
+    countdown(system_table.stdout, countdown_row, remaining)?;
+    let mut elapsed = 0;
+    loop {
+        let key = match (system_table.stdin.try_read_key()?, remaining) {
+            (Some(key), _) => key,
+            (None, Some(seconds)) => {
+                boot_services.stall(POLL)?;
+                elapsed += POLL;
+                if elapsed >= ONE_SECOND {
+                    elapsed = 0;
+                    if seconds <= 1 {
+                        return Ok((selected, None))
+                    }
+                    remaining = Some(seconds - 1);
+                    countdown(system_table.stdout, countdown_row, remaining)?;
+                }
+                continue
+            },
+            (None, None) => {
+                boot_services.wait_for_event(&[system_table.stdin.wait_for_key()])?;
+                continue
+            }
+        };
+        // Any key press means someone is there to choose
+        if remaining.take().is_some() {
+            countdown(system_table.stdout, countdown_row, None)?;
+        }
+        match key.decode() {
+            Keystroke::Scan(ScanCode::UP) if selected > 0 => selected -= 1,
+            Keystroke::Scan(ScanCode::DOWN) if selected + 1 < entries.len() => selected += 1,
+            Keystroke::Enter => return Ok((selected, None)),
+            Keystroke::Char('e') => {
+                let command_line = entries[selected].command_line;
+                // Truncate a command line too long to edit at a character boundary
+                let mut len = command_line.len().min(buffer.len());
+                while !command_line.is_char_boundary(len) {
+                    len -= 1;
+                }
+                buffer[..len].copy_from_slice(&command_line.as_bytes()[..len]);
+
+                let output = &mut *system_table.stdout;
+                output.set_cursor_position(0, countdown_row)?;
+                let _ = output.enable_cursor(true);
+                write!(output, "> ").map_err(|_| uefi::Error::DeviceError)?;
+                if let Some(len) = console::edit_line(buffer, len)?.map(str::len) {
+                    return Ok((selected, Some(len)))
+                }
+            },
+            _ => continue
+        }
+        draw(system_table.stdout, entries, selected)?;
+    }
+}
+
+/// Explain on a screen of its own why booting cannot continue, returning once a key is pressed
+pub fn alert(message: fmt::Arguments) {
+    let system_table = match unsafe { console::system_table() } {
+        Some(system_table) => system_table,
+        None => return
+    };
+    let output = &mut *system_table.stdout;
+    let _ = output.set_attribute(ALERT);
+    let _ = output.clear_screen();
+    let _ = output.enable_cursor(false);
+    let _ = writeln!(output, "Cherimoya cannot boot\n\n{}\n\nPress any key to return to the firmware", message);
+    // The watchdog must not reset the machine before the message has been read
+    let _ = system_table.boot_services.set_watchdog(0);
+    let _ = console::read_key();
+    let _ = system_table.boot_services.set_watchdog(WATCHDOG);
+    let _ = output.set_attribute(Attribute::DEFAULT);
+    let _ = output.clear_screen();
+    let _ = output.enable_cursor(true);
+}
+
+fn draw(output: &mut Output, entries: &[Entry], selected: usize) -> Result<(), uefi::Error> {
+    output.set_attribute(Attribute::DEFAULT)?;
+    output.clear_screen()?;
+    // Not every console can hide its cursor
+    let _ = output.enable_cursor(false);
+    let result = (|| {
+        writeln!(output, "Cherimoya\n")?;
+        for (i, entry) in entries.iter().enumerate() {
+            let _ = output.set_attribute(if i == selected { HIGHLIGHT } else { Attribute::DEFAULT });
+            writeln!(output, "  {}  ", entry.name)?;
+        }
+        let _ = output.set_attribute(Attribute::DEFAULT);
+        writeln!(output, "\nUp and down to select, enter to boot, e to edit the command line")
+    })();
+    result.map_err(|_| uefi::Error::DeviceError)
+}
+
+fn countdown(output: &mut Output, row: usize, remaining: Option<u32>) -> Result<(), uefi::Error> {
+    output.set_cursor_position(0, row)?;
+    match remaining {
+        Some(seconds) => write!(output, "Booting in {} seconds ", seconds),
+        None => write!(output, "{:32}", "")
+    }.map_err(|_| uefi::Error::DeviceError)
+}
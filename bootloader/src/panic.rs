@@ -0,0 +1,78 @@
+use core::fmt::Write;
+use serial::Serial;
+use crate::{console, uefi::{self, RuntimeServices}};
+
+/// What to do once a panic has been reported
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Reset the system immediately
+    Reset,
+    /// Wait for a key press before resetting, or halt if boot services have exited
+    WaitForKey,
+    /// Halt so that the report stays on screen, leaving the reset to whoever is there
+    Halt
+}
+
+static mut ACTION: Action = Action::WaitForKey;
+static mut RUNTIME_SERVICES: Option<&'static RuntimeServices> = None;
+static mut PANICKING: bool = false;
+
+pub fn init(runtime_services: &'static RuntimeServices, action: Action) {
+    unsafe {
+        RUNTIME_SERVICES = Some(runtime_services);
+        ACTION = action;
+    }
+}
+/// Change what happens after a panic, once the configuration has been read
+pub fn set_action(action: Action) {
+    unsafe { ACTION = action }
+}
+
+/// Stop using runtime services to reset, as they have moved to addresses only the kernel has mapped
+pub fn disable_reset() {
+    unsafe { RUNTIME_SERVICES = None }
+}
+
+#[cfg_attr(not(test), panic_handler)]
+#[cfg_attr(test, allow(dead_code))]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    unsafe {
+        // A panic while reporting a panic can only be made worse by trying again
+        if PANICKING {
+            halt()
+        }
+        PANICKING = true;
+
+        match console::system_table() {
+            Some(_) if ACTION == Action::Halt => {
+                println!("\nBootloader {}", info);
+                halt()
+            },
+            Some(system_table) => {
+                println!("\nBootloader {}", info);
+                if ACTION == Action::WaitForKey {
+                    println!("Press any key to reset");
+                    let _ = system_table.stdin.read_key(system_table.boot_services);
+                }
+            },
+            None => {
+                let _ = writeln!(Serial::new(Serial::COM1), "\nBootloader {}", info);
+                if ACTION != Action::Reset {
+                    halt()
+                }
+            }
+        }
+
+        match RUNTIME_SERVICES {
+            Some(runtime_services) => runtime_services.reset_system(uefi::ResetType::COLD, uefi::Status::ABORTED),
+            None => halt()
+        }
+    }
+}
+
+/// Stop the processor for good, for failures after boot services have exited
+pub fn halt() -> ! {
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) }
+    }
+}
@@ -2,30 +2,68 @@ use crate::void;
 
 macro_rules! opaque {
     ($name:ident) => {
+        #[derive(Copy, Clone)]
         #[repr(C)]
         pub struct $name(*mut [u8; 0]);
     };
 }
 
-mod protocol;
-mod mem;
-mod event;
-
-#[derive(Copy, Clone, PartialEq, Eq)]
-#[repr(transparent)]
-pub struct Status(pub usize);
-impl Status {
-    pub const SUCCESS: Self = Self(0);
-    pub const INVALID_PARAMETER: Self = Self(2 + (isize::MIN as usize));
-    pub const OUT_OF_RESOURCES: Self = Self(9 + isize::MIN as usize);
-}
+pub mod protocol;
+pub mod mem;
+pub mod event;
+pub mod configuration;
+pub mod runtime;
+mod guid;
+mod status;
+
+pub use configuration::ConfigurationTable;
+pub use guid::Guid;
+pub use runtime::{RuntimeServices, ResetType};
+pub use status::{Status, Error};
 
 opaque! { ImageHandle }
-opaque! { RuntimeServices }
-opaque! { ConfigurationTable }
 
-#[repr(C, align(64))]
-pub struct Guid(u32, u16, u16, [u8; 8]);
+/// Encode `string` as a null-terminated UCS-2 string in `buffer`.
+/// Fails if the string does not fit or has characters outside the Basic Multilingual Plane.
+pub fn encode_ucs2(string: &str, buffer: &mut [u16]) -> Result<(), Error> {
+    let mut len = 0;
+    for c in string.chars() {
+        if len + 1 >= buffer.len() || c as u32 > 0xFFFF {
+            return Err(Error::InvalidParameter)
+        }
+        buffer[len] = c as u16;
+        len += 1;
+    }
+    *buffer.get_mut(len).ok_or(Error::InvalidParameter)? = 0;
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    _pad: u8,
+    pub nanosecond: u32,
+    /// Offset from UTC in minutes, or `Time::UNSPECIFIED_TIMEZONE` for local time
+    pub timezone: i16,
+    pub daylight: u8,
+    _pad2: u8
+}
+impl Time {
+    pub const UNSPECIFIED_TIMEZONE: i16 = 0x07FF;
+}
+impl From<&Time> for bootinfo::Time {
+    fn from(time: &Time) -> Self {
+        // UEFI defines local time as UTC minus the timezone
+        let timezone = if time.timezone == Time::UNSPECIFIED_TIMEZONE { 0 } else { -time.timezone };
+        Self::new(time.year, time.month, time.day, time.hour, time.minute, time.second, time.nanosecond, timezone)
+    }
+}
 
 #[repr(C)]
 pub struct TableHeader {
@@ -41,16 +79,33 @@ pub struct SystemTable {
     header: TableHeader,
     firmware_vendor: *const u16,
     firmware_version: u32,
-    stdin_protocol: protocol::Protocol,
+    stdin_protocol: protocol::Handle,
     pub stdin: &'static mut protocol::console::Input,
-    stdout_protocol: protocol::Protocol,
+    stdout_protocol: protocol::Handle,
     pub stdout: &'static mut protocol::console::Output,
-    stderr_protocol: protocol::Protocol,
+    stderr_protocol: protocol::Handle,
     pub stderr: &'static mut protocol::console::Output,
-    runtime_services: &'static RuntimeServices,
+    pub runtime_services: &'static RuntimeServices,
     pub boot_services: &'static BootServices,
     table_entries: usize,
-    configuration: ConfigurationTable
+    configuration: *const ConfigurationTable
+}
+impl SystemTable {
+    /// The vendor tables installed by the firmware
+    pub fn configuration_tables(&self) -> impl Iterator<Item=(Guid, *const void)> + '_ {
+        let tables = if self.configuration.is_null() {
+            &[]
+        } else {
+            unsafe { core::slice::from_raw_parts(self.configuration, self.table_entries) }
+        };
+        tables.iter().map(|table| (table.guid, table.table))
+    }
+    /// Find the vendor table identified by `guid`
+    pub fn find_table(&self, guid: &Guid) -> Option<*const void> {
+        self.configuration_tables()
+            .find(|(table_guid, _)| table_guid == guid)
+            .map(|(_, table)| table)
+    }
 }
 
 #[repr(C)]
@@ -68,18 +123,18 @@ pub struct BootServices {
 
     create_event: extern "efiapi" fn(event_type: event::Type, notify_priority: event::Priority, notify_fn: event::NotifyFn, context: *mut void, event: &mut event::Event) -> Status,
     set_timer: extern "efiapi" fn(event::Event, event::TimerType, time: u64) -> Status,
-    wait_for_event: extern "efiapi" fn(count: usize, events: event::Event, waited: &mut usize) -> Status,
+    wait_for_event: extern "efiapi" fn(count: usize, events: *const event::Event, index: &mut usize) -> Status,
     signal_event: extern "efiapi" fn(event::Event) -> Status,
     close_event: extern "efiapi" fn(event::Event) -> Status,
     check_event: extern "efiapi" fn(event::Event) -> Status,
 
-    install_protocol:  extern "efiapi" fn(&mut protocol::Protocol, protocol: &Guid, protocol::InterfaceType, protocol::Interface) -> Status,
-    reinstall_protocol: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, old: protocol::Interface, new: protocol::Interface) -> Status,
-    uninstall_protocol: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, protocol::Interface) -> Status,
-    handle_protocol: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, &mut protocol::Interface) -> Status,
+    install_protocol:  extern "efiapi" fn(&mut protocol::Handle, protocol: &Guid, protocol::InterfaceType, protocol::Interface) -> Status,
+    reinstall_protocol: extern "efiapi" fn(protocol::Handle, protocol: &Guid, old: protocol::Interface, new: protocol::Interface) -> Status,
+    uninstall_protocol: extern "efiapi" fn(protocol::Handle, protocol: &Guid, protocol::Interface) -> Status,
+    handle_protocol: extern "efiapi" fn(protocol::Handle, protocol: &Guid, &mut protocol::Interface) -> Status,
     _reserved: *const void,
     register_protocol_notify: extern "efiapi" fn(protocol: &Guid, event::Event, registration: *mut *const void) -> Status,
-    locate_handle: extern "efiapi" fn(protocol::SearchType, protocol: Option<&Guid>, key: *const void, buffer_size: &mut usize, buffer: &mut protocol::Protocol) -> Status,
+    locate_handle: extern "efiapi" fn(protocol::SearchType, protocol: Option<&Guid>, key: *const void, buffer_size: &mut usize, buffer: &mut protocol::Handle) -> Status,
     locate_device_path: extern "efiapi" fn(protocol: &Guid, &mut &protocol::device::Path, device: &mut protocol::device::Device) -> Status,
     install_configuration_table: extern "efiapi" fn() -> Status,
     
@@ -89,20 +144,20 @@ pub struct BootServices {
     unload_image: extern "efiapi" fn() -> Status,
     exit_boot_services: extern "efiapi" fn(ImageHandle, usize) -> Status,
 
-    next_monotonic_count: extern "efiapi" fn() -> Status,
-    stall: extern "efiapi" fn() -> Status,
-    set_watchdog: extern "efiapi" fn() -> Status,
+    next_monotonic_count: extern "efiapi" fn(count: &mut u64) -> Status,
+    stall: extern "efiapi" fn(microseconds: usize) -> Status,
+    set_watchdog: extern "efiapi" fn(timeout: usize, code: u64, data_size: usize, data: *const u16) -> Status,
 
     connect_controller: extern "efiapi" fn(protocol::Controller, drivers: *const ImageHandle, Option<&protocol::device::RemainingPath>, recursive: u8) -> Status,
     disconnect_controller: extern "efiapi" fn(protocol::Controller, driver: ImageHandle, child: ImageHandle) -> Status,
 
-    open_protocol: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, Option<&mut protocol::Interface>, protocol::Agent, protocol::Controller, attributes: protocol::Attributes) -> Status,
-    close_protocol: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, protocol::Agent, protocol::Controller) -> Status,
-    open_protocol_info: extern "efiapi" fn(protocol::Protocol, protocol: &Guid, entries: &mut *const protocol::Information, entry_count: &mut usize) -> Status,
+    open_protocol: extern "efiapi" fn(protocol::Handle, protocol: &Guid, Option<&mut protocol::Interface>, protocol::Agent, protocol::Controller, attributes: protocol::Attributes) -> Status,
+    close_protocol: extern "efiapi" fn(protocol::Handle, protocol: &Guid, protocol::Agent, protocol::Controller) -> Status,
+    open_protocol_info: extern "efiapi" fn(protocol::Handle, protocol: &Guid, entries: &mut *const protocol::Information, entry_count: &mut usize) -> Status,
 
     protocols_per_handle: extern "efiapi" fn() -> Status,
-    locate_handle_buffer: extern "efiapi" fn() -> Status,
-    locate_protocol: extern "efiapi" fn() -> Status,
+    locate_handle_buffer: extern "efiapi" fn(protocol::SearchType, protocol: Option<&Guid>, key: *const void, count: &mut usize, buffer: &mut *mut protocol::Handle) -> Status,
+    locate_protocol: extern "efiapi" fn(protocol: &Guid, registration: *const void, interface: &mut protocol::Interface) -> Status,
     install_multiple_protocols: extern "efiapi" fn() -> Status,
     uninstall_multiple_protocols: extern "efiapi" fn() -> Status,
 
@@ -113,52 +168,157 @@ pub struct BootServices {
     create_event_ex: extern "efiapi" fn(event_type: event::Type, notify_priority: event::Priority, notify_fn: event::NotifyFn, context: *const void, group: &Guid, event: &mut event::Event) -> Status
 }
 impl BootServices {
-    pub fn get_memory_map(&'static self) -> Option<mem::MemoryMap> {
+    /// Block until one of `events` is signalled, returning its index
+    pub fn wait_for_event(&self, events: &[event::Event]) -> Result<usize, Error> {
+        let mut index = 0;
+        (self.wait_for_event)(events.len(), events.as_ptr(), &mut index).into_result()?;
+        Ok(index)
+    }
+    pub fn get_memory_map(&'static self) -> Result<mem::MemoryMap, Error> {
         let mut total_size = 0;
         let mut descriptors = 0 as *mut _;
         let mut descriptor_size = 0;
         let mut version = 0;
         let mut key = 0;
+        // Expected to fail with BUFFER_TOO_SMALL, reporting the required size
         (self.get_memory_map)(&mut total_size, descriptors, &mut key, &mut descriptor_size, &mut version);
 
-        total_size += 2 * descriptor_size;
+        // Leave room for the map to grow, both from this allocation and from any made before exiting boot services
+        total_size += 8 * descriptor_size;
+        let capacity = total_size;
 
         descriptors = self.allocate_pool_untyped(total_size, mem::MemoryType::MEMORY_MAP)? as _;
 
-        if (self.get_memory_map)(&mut total_size, descriptors, &mut key, &mut descriptor_size, &mut version) == Status::SUCCESS {
-            Some(mem::MemoryMap {
+        if let Err(error) = (self.get_memory_map)(&mut total_size, descriptors, &mut key, &mut descriptor_size, &mut version).into_result() {
+            let _ = self.free_pool(descriptors);
+            return Err(error)
+        }
+        Ok(mem::MemoryMap {
+            map: mem::FinalMemoryMap {
                 total_size,
                 descriptors,
                 descriptor_size,
-                version,
-                key,
-                boot_services: self
-            })
-        } else {
-            self.free_pool(descriptors);
-            None
-        }
+                version
+            },
+            capacity,
+            key,
+            boot_services: self
+        })
     }
-    pub fn allocate_pool<T>(&self, count: usize, memory_type: mem::MemoryType) -> Option<*mut T> {
-        let buffer = 0 as *mut T;
-        if (self.allocate_pool)(memory_type, core::mem::size_of::<T>() * count, &mut (buffer as _)) == Status::SUCCESS {
-            Some(buffer)
-        } else {
-            None
-        }
+    /// Fetch the current memory map into the buffer of an existing map without allocating
+    fn refresh_memory_map(&self, memory_map: &mut mem::MemoryMap) -> Result<(), Error> {
+        let mut total_size = memory_map.capacity;
+        (self.get_memory_map)(&mut total_size, memory_map.map.descriptors, &mut memory_map.key, &mut memory_map.map.descriptor_size, &mut memory_map.map.version).into_result()?;
+        memory_map.map.total_size = total_size;
+        Ok(())
+    }
+    pub fn allocate_pool<T>(&self, count: usize, memory_type: mem::MemoryType) -> Result<*mut T, Error> {
+        self.allocate_pool_untyped(core::mem::size_of::<T>() * count, memory_type).map(|buffer| buffer as _)
     }
-    pub fn allocate_pool_untyped(&self, bytes: usize, memory_type: mem::MemoryType) -> Option<*mut void> {
+    pub fn allocate_pool_untyped(&self, bytes: usize, memory_type: mem::MemoryType) -> Result<*mut void, Error> {
         let mut buffer = 0 as *mut void;
-        if (self.allocate_pool)(memory_type, bytes, &mut buffer) == Status::SUCCESS {
-            Some(buffer)
-        } else {
-            None
+        (self.allocate_pool)(memory_type, bytes, &mut buffer).into_result()?;
+        Ok(buffer)
+    }
+    pub fn free_pool<T>(&self, pool: *mut T) -> Result<(), Error> {
+        (self.free_pool)(pool as _).into_result()
+    }
+    /// Allocate `pages` contiguous 4K pages anywhere in physical memory
+    pub fn allocate_pages(&self, memory_type: mem::MemoryType, pages: usize) -> Result<*mut kalloc::Page, Error> {
+        let mut memory = 0;
+        (self.allocate_pages)(mem::AllocateType::ANY_PAGES, memory_type, pages, &mut memory).into_result()?;
+        Ok(memory as _)
+    }
+    pub fn free_pages(&self, memory: *mut kalloc::Page, pages: usize) -> Result<(), Error> {
+        (self.free_pages)(memory as _, pages).into_result()
+    }
+    /// Get the protocol interface installed on a handle
+    pub fn handle_protocol<P: protocol::Protocol>(&self, handle: protocol::Handle) -> Result<&'static mut P, Error> {
+        let mut interface = protocol::Interface::NULL;
+        (self.handle_protocol)(handle, &P::GUID, &mut interface).into_result()?;
+        // Safe: the protocol trait guarantees the interface has the layout of P
+        unsafe { interface.cast() }.ok_or(Error::Unsupported)
+    }
+    /// Get the first instance of a protocol installed on any handle
+    pub fn locate_protocol<P: protocol::Protocol>(&self) -> Result<&'static mut P, Error> {
+        let mut interface = protocol::Interface::NULL;
+        (self.locate_protocol)(&P::GUID, core::ptr::null(), &mut interface).into_result()?;
+        // Safe: the protocol trait guarantees the interface has the layout of P
+        unsafe { interface.cast() }.ok_or(Error::Unsupported)
+    }
+    /// Open a protocol on a handle on behalf of `agent`, closing it again once the returned guard is dropped
+    pub fn open<P: protocol::Protocol>(&'static self, handle: protocol::Handle, agent: ImageHandle) -> Result<protocol::Opened<P>, Error> {
+        let mut interface = protocol::Interface::NULL;
+        let agent = protocol::Agent::from(agent);
+        (self.open_protocol)(handle, &P::GUID, Some(&mut interface), agent, protocol::Controller::NULL, protocol::Attributes::BY_HANDLE_PROTOCOL).into_result()?;
+        // Safe: the protocol trait guarantees the interface has the layout of P
+        match unsafe { interface.cast() } {
+            Some(interface) => Ok(protocol::Opened {
+                interface,
+                handle,
+                agent,
+                boot_services: self
+            }),
+            None => {
+                let _ = (self.close_protocol)(handle, &P::GUID, agent, protocol::Controller::NULL);
+                Err(Error::Unsupported)
+            }
         }
     }
-    pub fn free_pool<T>(&self, pool: *mut T) -> Status {
-        (self.free_pool)(pool as _)
+    /// Close a protocol opened by `open`
+    pub(in crate::uefi) fn close<P: protocol::Protocol>(&self, handle: protocol::Handle, agent: protocol::Agent) -> Result<(), Error> {
+        (self.close_protocol)(handle, &P::GUID, agent, protocol::Controller::NULL).into_result()
+    }
+    /// Find every handle that supports a protocol
+    pub fn locate_handle_buffer<P: protocol::Protocol>(&'static self) -> Result<protocol::HandleBuffer, Error> {
+        let mut count = 0;
+        let mut handles = core::ptr::null_mut();
+        (self.locate_handle_buffer)(protocol::SearchType::BY_PROTOCOL, Some(&P::GUID), core::ptr::null(), &mut count, &mut handles).into_result()?;
+        Ok(protocol::HandleBuffer {
+            handles,
+            count,
+            boot_services: self
+        })
     }
-    pub fn exit_boot_services(&self, program: ImageHandle, memory_map: &mem::MemoryMap) -> Status {
-        (self.exit_boot_services)(program, memory_map.key)
+    /// A counter that only ever increases while boot services are running
+    pub fn next_monotonic_count(&self) -> Result<u64, Error> {
+        let mut count = 0;
+        (self.next_monotonic_count)(&mut count).into_result()?;
+        Ok(count)
+    }
+    /// Busy wait for at least `microseconds`
+    #[inline]
+    pub fn stall(&self, microseconds: usize) -> Result<(), Error> {
+        (self.stall)(microseconds).into_result()
+    }
+    /// Reset the system if the watchdog is not set again within `seconds`, or disarm it if `seconds` is 0.
+    /// The firmware arms a 5 minute watchdog before starting the bootloader.
+    pub fn set_watchdog(&self, seconds: usize) -> Result<(), Error> {
+        // Codes below 0x10000 are reserved for the firmware
+        const CODE: u64 = 0x10000;
+        (self.set_watchdog)(seconds, CODE, 0, core::ptr::null()).into_result()
+    }
+    /// Exit boot services, re-fetching the memory map into its existing buffer whenever the firmware reports the map key is stale.
+    /// On success the returned map is the final memory map and nothing may call into boot services again.
+    /// On failure the memory map buffer is leaked as boot services may have been partially shut down.
+    pub fn exit_boot_services(&self, program: ImageHandle, mut memory_map: mem::MemoryMap) -> Result<mem::FinalMemoryMap, Error> {
+        const ATTEMPTS: usize = 8;
+        let mut result = Err(Error::InvalidParameter);
+        for _ in 0..ATTEMPTS {
+            result = (self.exit_boot_services)(program, memory_map.key).into_result();
+            match result {
+                Ok(()) => return Ok(memory_map.into_final()),
+                Err(Error::InvalidParameter) => (),
+                Err(_) => break
+            }
+            // Only GetMemoryMap may be called after a failed ExitBootServices, so the existing buffer has to do
+            result = self.refresh_memory_map(&mut memory_map);
+            if result.is_err() {
+                break
+            }
+        }
+        memory_map.into_final();
+        // Running out of attempts leaves the stale map key as the error
+        result.and(Err(Error::InvalidParameter))
     }
 }
\ No newline at end of file
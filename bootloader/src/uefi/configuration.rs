@@ -0,0 +1,19 @@
+use crate::{void, uefi::Guid};
+
+#[repr(C)]
+pub struct ConfigurationTable {
+    pub guid: Guid,
+    pub table: *const void
+}
+impl ConfigurationTable {
+    /// The ACPI 1.0 RSDP
+    pub const ACPI_GUID: Guid = Guid::new(0xEB9D2D30, 0x2D88, 0x11D3, [0x9A, 0x16, 0x00, 0x90, 0x27, 0x3F, 0xC1, 0x4D]);
+    /// The ACPI 2.0 or later RSDP
+    pub const ACPI_20_GUID: Guid = Guid::new(0x8868E871, 0xE4F1, 0x11D3, [0xBC, 0x22, 0x00, 0x80, 0xC7, 0x3C, 0x88, 0x81]);
+    /// The SMBIOS 2.x entry point
+    pub const SMBIOS_GUID: Guid = Guid::new(0xEB9D2D31, 0x2D88, 0x11D3, [0x9A, 0x16, 0x00, 0x90, 0x27, 0x3F, 0xC1, 0x4D]);
+    /// The SMBIOS 3.x entry point
+    pub const SMBIOS3_GUID: Guid = Guid::new(0xF2FD1544, 0x9794, 0x4A2C, [0x99, 0x2E, 0xE5, 0xBB, 0xCF, 0x20, 0xE3, 0x94]);
+    /// A flattened device tree blob
+    pub const DTB_GUID: Guid = Guid::new(0xB1B621D5, 0xF19C, 0x41A5, [0x83, 0x0B, 0xD9, 0x15, 0x2C, 0x69, 0xAA, 0xE0]);
+}
@@ -19,8 +19,3 @@ impl Type {
 
 #[repr(transparent)]
 pub struct TimerType(u32);
-impl Type {
-    pub const CANCEL: Self = Self(1);
-    pub const PERIODIC: Self = Self(2);
-    pub const RELATIVE: Self = Self(3);
-}
\ No newline at end of file
@@ -0,0 +1,138 @@
+use core::{fmt, str::FromStr};
+
+/// A 128-bit EFI_GUID, 8-byte aligned as required by the specification
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C, align(8))]
+pub struct Guid(u32, u16, u16, [u8; 8]);
+impl Guid {
+    /// Construct a GUID from the fields of its canonical form `aaaaaaaa-bbbb-cccc-dddd-dddddddddddd`
+    pub const fn new(a: u32, b: u16, c: u16, d: [u8; 8]) -> Self {
+        Self(a, b, c, d)
+    }
+    /// Read a GUID from its 16 byte in-memory representation
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        let mut d = [0; 8];
+        d.copy_from_slice(&bytes[8..]);
+        Self(
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            u16::from_le_bytes([bytes[4], bytes[5]]),
+            u16::from_le_bytes([bytes[6], bytes[7]]),
+            d
+        )
+    }
+}
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = &self.3;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            self.0, self.1, self.2, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+        )
+    }
+}
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// The string is not a GUID in the canonical `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` form
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseGuidError;
+impl fmt::Display for ParseGuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid GUID syntax")
+    }
+}
+
+impl FromStr for Guid {
+    type Err = ParseGuidError;
+    /// Parse the canonical form, with or without surrounding braces
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(s);
+        let bytes = s.as_bytes();
+        if bytes.len() != 36 || bytes[8] != b'-' || bytes[13] != b'-' || bytes[18] != b'-' || bytes[23] != b'-' {
+            return Err(ParseGuidError)
+        }
+        let hex = |range: core::ops::Range<usize>| {
+            let digits = &s[range];
+            // from_str_radix would also accept a leading sign
+            if digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                u64::from_str_radix(digits, 16).map_err(|_| ParseGuidError)
+            } else {
+                Err(ParseGuidError)
+            }
+        };
+
+        let mut d = [0; 8];
+        let clock = hex(19..23)?;
+        d[0] = (clock >> 8) as u8;
+        d[1] = clock as u8;
+        let node = hex(24..36)?;
+        for (i, byte) in d[2..].iter_mut().enumerate() {
+            *byte = (node >> (8 * (5 - i))) as u8;
+        }
+        Ok(Self(hex(0..8)? as u32, hex(9..13)? as u16, hex(14..18)? as u16, d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACPI_20: Guid = Guid::new(0x8868E871, 0xE4F1, 0x11D3, [0xBC, 0x22, 0x00, 0x80, 0xC7, 0x3C, 0x88, 0x81]);
+
+    #[test]
+    fn layout() {
+        assert_eq!(core::mem::size_of::<Guid>(), 16);
+        assert_eq!(core::mem::align_of::<Guid>(), 8);
+    }
+    #[test]
+    fn display() {
+        assert_eq!(ACPI_20.to_string(), "8868e871-e4f1-11d3-bc22-0080c73c8881");
+    }
+    #[test]
+    fn round_trip() {
+        assert_eq!(ACPI_20.to_string().parse(), Ok(ACPI_20));
+        let zero = Guid::new(0, 0, 0, [0; 8]);
+        assert_eq!(zero.to_string().parse(), Ok(zero));
+        let ones = Guid::new(u32::MAX, u16::MAX, u16::MAX, [0xFF; 8]);
+        assert_eq!(ones.to_string().parse(), Ok(ones));
+    }
+    #[test]
+    fn parse() {
+        assert_eq!("8868E871-E4F1-11D3-BC22-0080C73C8881".parse(), Ok(ACPI_20));
+        assert_eq!("{8868e871-e4f1-11d3-bc22-0080c73c8881}".parse(), Ok(ACPI_20));
+    }
+    #[test]
+    fn from_bytes() {
+        let bytes = [0x71, 0xE8, 0x68, 0x88, 0xF1, 0xE4, 0xD3, 0x11, 0xBC, 0x22, 0x00, 0x80, 0xC7, 0x3C, 0x88, 0x81];
+        assert_eq!(Guid::from_bytes(bytes), ACPI_20);
+    }
+    #[test]
+    fn reject_length() {
+        assert_eq!("".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f1-11d3-bc22-0080c73c888".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f1-11d3-bc22-0080c73c88810".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("{8868e871-e4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("{{8868e871-e4f1-11d3-bc22-0080c73c8881}}".parse::<Guid>(), Err(ParseGuidError));
+    }
+    #[test]
+    fn reject_hex() {
+        assert_eq!("8868e87g-e4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f1-11d3-bc22-0080c73c888 ".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("+868e871-e4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-+4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        // 36 bytes with the dashes in place, but a two byte character in a group
+        assert_eq!("8868e8é-e4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f1-11d3-bc22-0080c73c88é".parse::<Guid>(), Err(ParseGuidError));
+    }
+    #[test]
+    fn reject_dashes() {
+        assert_eq!("8868e871e-4f1-11d3-bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f111d3--bc22-0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871-e4f1-11d3-bc220080c73c8881-".parse::<Guid>(), Err(ParseGuidError));
+        assert_eq!("8868e871_e4f1_11d3_bc22_0080c73c8881".parse::<Guid>(), Err(ParseGuidError));
+    }
+}
@@ -1,6 +1,10 @@
-use core::ops::{Deref, DerefMut, BitOr, BitXor, BitAnd, Not, Index};
+use core::ops::{Deref, DerefMut, BitOr, BitXor, BitAnd, Not};
+use kalloc::{MemoryProperties, MemorySegment, MemoryUsage};
 use crate::uefi;
 
+/// The size of the pages counted by `BootServices::allocate_pages` and the memory map
+pub const PAGE_SIZE: usize = 0x1000;
+
 #[repr(transparent)]
 pub struct AllocateType(u32);
 impl AllocateType {
@@ -8,6 +12,7 @@ impl AllocateType {
     pub const MAX_ADDRESS: Self = Self(1);
     pub const ADDRESS: Self = Self(2);
 }
+#[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct MemoryType(u32);
 impl MemoryType {
@@ -21,13 +26,24 @@ impl MemoryType {
     pub const CONVENTIONAL: Self = Self(7);
     pub const UNUSABLE: Self = Self(8);
     pub const ACPI_RECLAIM: Self = Self(9);
-    pub const ACPI: Self = Self(9);
-    pub const MMIO: Self = Self(10);
-    pub const MMIO_PORT: Self = Self(11);
-    pub const PAL: Self = Self(12);
-    pub const PERSISTENT: Self = Self(13);
+    pub const ACPI_NVS: Self = Self(10);
+    pub const MMIO: Self = Self(11);
+    pub const MMIO_PORT: Self = Self(12);
+    pub const PAL: Self = Self(13);
+    pub const PERSISTENT: Self = Self(14);
+    /// OS-defined: the loaded kernel segments
+    pub const KERNEL: Self = Self(0x80000000);
+    /// OS-defined: page tables built for the kernel
+    pub const PAGE_TABLE: Self = Self(0x80000001);
+    /// OS-defined: pages owned by the kernel's page allocator
+    pub const ALLOCATOR: Self = Self(0x80000002);
+    /// OS-defined: the boot info structure and the data it references
+    pub const BOOT_INFO: Self = Self(0x80000003);
+    /// OS-defined: the initial ramdisk
+    pub const INITRD: Self = Self(0x80000004);
     pub const MEMORY_MAP: Self = Self(-1i32 as u32);
 }
+#[derive(Copy, Clone)]
 #[repr(transparent)]
 pub struct MemoryAttributes(u64);
 impl MemoryAttributes {
@@ -45,6 +61,11 @@ impl MemoryAttributes {
     pub const SPECIFIC_PURPOSE: Self = Self(0x40000);
     pub const CRYPTO_PROTECTED: Self = Self(0x80000);
     pub const RUNTIME: Self = Self(0x8000000000000000);
+
+    #[inline]
+    pub fn contains(self, attributes: Self) -> bool {
+        *self & *attributes == *attributes
+    }
 }
 impl BitOr for MemoryAttributes {
     type Output = Self;
@@ -82,33 +103,64 @@ impl DerefMut for MemoryAttributes {
     }
 }
 
+/// A memory map that owns its buffer through boot services
 pub struct MemoryMap {
-    pub(in crate::uefi) descriptors: *mut MemoryDescriptor,
-    pub total_size: usize,
-    pub descriptor_size: usize,
-    pub(in crate::uefi) version: u32,
+    pub(in crate::uefi) map: FinalMemoryMap,
+    /// The size of the buffer, which may be larger than the map it holds
+    pub(in crate::uefi) capacity: usize,
     pub(in crate::uefi) key: usize,
     pub(in crate::uefi) boot_services: &'static uefi::BootServices
 }
 impl MemoryMap {
-    pub fn iter(&self) -> MemoryMapIter<'_> {
-        MemoryMapIter(0, self)
+    /// Give up ownership of the buffer without returning it to boot services
+    pub(in crate::uefi) fn into_final(self) -> FinalMemoryMap {
+        let map = core::mem::ManuallyDrop::new(self);
+        // Safe: `self` is never dropped so the map is not duplicated
+        unsafe { core::ptr::read(&map.map) }
+    }
+}
+impl Deref for MemoryMap {
+    type Target = FinalMemoryMap;
+    fn deref(&self) -> &Self::Target {
+        &self.map
     }
 }
 impl Drop for MemoryMap {
     fn drop(&mut self) {
-        self.boot_services.free_pool(self.descriptors);
+        // Drop cannot report errors and a pool that fails to free is only leaked until boot services exit
+        let _ = self.boot_services.free_pool(self.map.descriptors);
+    }
+}
+/// The memory map boot services were exited with.
+/// It has no access to boot services so it can safely outlive them.
+pub struct FinalMemoryMap {
+    pub(in crate::uefi) descriptors: *mut MemoryDescriptor,
+    pub total_size: usize,
+    pub descriptor_size: usize,
+    pub(in crate::uefi) version: u32
+}
+impl FinalMemoryMap {
+    pub fn iter(&self) -> MemoryMapIter<'_> {
+        MemoryMapIter(0, self)
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=&mut MemoryDescriptor> + '_ {
+        let descriptors = self.descriptors as usize;
+        let descriptor_size = self.descriptor_size;
+        // Safe: each descriptor is visited once so the mutable references never alias
+        (0..self.total_size / self.descriptor_size)
+            .filter_map(move |i| unsafe { ((descriptors + descriptor_size * i) as *mut MemoryDescriptor).as_mut() })
     }
 }
-pub struct MemoryMapIter<'a>(usize, &'a MemoryMap);
+pub struct MemoryMapIter<'a>(usize, &'a FinalMemoryMap);
 impl<'a> Iterator for MemoryMapIter<'a> {
     type Item = &'a MemoryDescriptor;
     fn next(&mut self) -> Option<Self::Item> {
-        self.0 += 1;
-        if self.1.descriptor_size * self.0 >= self.1.total_size {
+        if self.1.descriptor_size * (self.0 + 1) > self.1.total_size {
             None
         } else {
-            unsafe { ((self.1.descriptors as usize + self.1.descriptor_size * self.0) as *mut MemoryDescriptor).as_ref() }
+            let descriptor = unsafe { ((self.1.descriptors as usize + self.1.descriptor_size * self.0) as *mut MemoryDescriptor).as_ref() };
+            self.0 += 1;
+            descriptor
         }
     }
 }
@@ -120,4 +172,72 @@ pub struct MemoryDescriptor {
     pub virtual_start: u64,
     pub pages: u64,
     pub attributes: MemoryAttributes
+}
+impl MemoryDescriptor {
+    /// Describe the memory as seen by the kernel once boot services have exited
+    pub fn segment(&self) -> MemorySegment {
+        MemorySegment {
+            page: self.physcial_start as _,
+            count: self.pages as _,
+            usage: self.memory_type.into(),
+            properties: self.attributes.into()
+        }
+    }
+}
+
+impl From<MemoryType> for MemoryUsage {
+    fn from(memory_type: MemoryType) -> Self {
+        match memory_type {
+            // Nothing from the bootloader or boot services survives the handoff
+            MemoryType::CONVENTIONAL
+            | MemoryType::LOADER_CODE
+            | MemoryType::LOADER_DATA
+            | MemoryType::BOOT_SERVICES_CODE
+            | MemoryType::BOOT_SERVICES_DATA => MemoryUsage::Free,
+            MemoryType::ACPI_RECLAIM
+            | MemoryType::BOOT_INFO
+            | MemoryType::MEMORY_MAP => MemoryUsage::Reclaimable,
+            MemoryType::MMIO
+            | MemoryType::MMIO_PORT => MemoryUsage::Mmio,
+            MemoryType::UNUSABLE => MemoryUsage::Unusable,
+            MemoryType::KERNEL
+            | MemoryType::PAGE_TABLE => MemoryUsage::Kernel,
+            MemoryType::ALLOCATOR => MemoryUsage::Allocator,
+            MemoryType::INITRD => MemoryUsage::Initrd,
+            _ => MemoryUsage::Reserved
+        }
+    }
+}
+impl From<MemoryAttributes> for MemoryProperties {
+    fn from(attributes: MemoryAttributes) -> Self {
+        let mut properties = 0;
+        if !attributes.contains(MemoryAttributes::READ_PROTECTED) {
+            properties |= MemoryProperties::READ;
+        }
+        if !attributes.contains(MemoryAttributes::WRITE_PROTECTED) && !attributes.contains(MemoryAttributes::READ_ONLY) {
+            properties |= MemoryProperties::WRITE;
+        }
+        if !attributes.contains(MemoryAttributes::EXECUTE_PROTECTED) {
+            properties |= MemoryProperties::EXECUTE;
+        }
+        if attributes.contains(MemoryAttributes::NO_CACHE) {
+            properties |= MemoryProperties::UNCACHEABLE;
+        }
+        if attributes.contains(MemoryAttributes::WRITE_COMBINING) {
+            properties |= MemoryProperties::WRITE_COMBINING;
+        }
+        if attributes.contains(MemoryAttributes::WRITE_THROUGH) {
+            properties |= MemoryProperties::WRITE_THROUGH;
+        }
+        if attributes.contains(MemoryAttributes::WRITE_BACK) {
+            properties |= MemoryProperties::WRITE_BACK;
+        }
+        if attributes.contains(MemoryAttributes::NON_VOLATILE) {
+            properties |= MemoryProperties::NON_VOLATILE;
+        }
+        if attributes.contains(MemoryAttributes::RUNTIME) {
+            properties |= MemoryProperties::RUNTIME;
+        }
+        MemoryProperties::new(properties)
+    }
 }
\ No newline at end of file
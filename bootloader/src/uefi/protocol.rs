@@ -1,11 +1,50 @@
 pub mod console;
 pub mod device;
+pub mod file;
+pub mod graphics;
+pub mod image;
+pub mod mp;
+pub mod rng;
+pub mod serial;
 
-opaque! { Protocol }
+use core::ops::{Deref, DerefMut};
+use crate::uefi::{BootServices, Guid, ImageHandle};
+
+/// A protocol interface identified by a GUID.
+/// Interfaces belong to the firmware rather than to any borrow, so they are handed out as `&'static mut`.
+/// # Safety
+/// `GUID` must identify a protocol whose interface has the layout of the implementing type
+pub unsafe trait Protocol: 'static {
+    const GUID: Guid;
+}
+
+opaque! { Handle }
 opaque! { Interface }
 opaque! { Agent }
 opaque! { Controller }
 
+impl From<ImageHandle> for Handle {
+    fn from(image: ImageHandle) -> Self {
+        Self(image.0)
+    }
+}
+impl From<ImageHandle> for Agent {
+    fn from(image: ImageHandle) -> Self {
+        Self(image.0)
+    }
+}
+impl Controller {
+    pub const NULL: Self = Self(core::ptr::null_mut());
+}
+impl Interface {
+    pub const NULL: Self = Self(core::ptr::null_mut());
+    /// # Safety
+    /// The interface must point to an instance of `T`
+    pub unsafe fn cast<T>(self) -> Option<&'static mut T> {
+        (self.0 as *mut T).as_mut()
+    }
+}
+
 #[repr(transparent)]
 pub struct InterfaceType(u32);
 impl InterfaceType {
@@ -21,7 +60,7 @@ impl SearchType {
 #[repr(transparent)]
 pub struct Attributes(u32);
 impl Attributes {
-    pub const BY_PROTOCOL: Self = Self(0x1);
+    pub const BY_HANDLE_PROTOCOL: Self = Self(0x1);
     pub const GET_PROTOCOL: Self = Self(0x2);
     pub const TEST_PROTOCOL: Self = Self(0x4);
     pub const BY_CHILD_CONTROLLER: Self = Self(0x8);
@@ -35,4 +74,58 @@ pub struct Information {
     controller: Controller,
     attributes: Attributes,
     open_count: u32
-}
\ No newline at end of file
+}
+/// A protocol opened with `BootServices::open`, closed again when dropped
+pub struct Opened<P: Protocol> {
+    pub(in crate::uefi) interface: &'static mut P,
+    pub(in crate::uefi) handle: Handle,
+    pub(in crate::uefi) agent: Agent,
+    pub(in crate::uefi) boot_services: &'static BootServices
+}
+impl<P: Protocol> Opened<P> {
+    /// The handle the protocol was opened on
+    #[inline]
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+}
+impl<P: Protocol> Deref for Opened<P> {
+    type Target = P;
+    fn deref(&self) -> &P {
+        self.interface
+    }
+}
+impl<P: Protocol> DerefMut for Opened<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        self.interface
+    }
+}
+impl<P: Protocol> Drop for Opened<P> {
+    fn drop(&mut self) {
+        let _ = self.boot_services.close::<P>(self.handle, self.agent);
+    }
+}
+
+/// A pool allocated array of handles returned by `BootServices::locate_handle_buffer`
+pub struct HandleBuffer {
+    pub(in crate::uefi) handles: *mut Handle,
+    pub(in crate::uefi) count: usize,
+    pub(in crate::uefi) boot_services: &'static BootServices
+}
+impl Deref for HandleBuffer {
+    type Target = [Handle];
+    fn deref(&self) -> &[Handle] {
+        if self.handles.is_null() {
+            &[]
+        } else {
+            unsafe { core::slice::from_raw_parts(self.handles, self.count) }
+        }
+    }
+}
+impl Drop for HandleBuffer {
+    fn drop(&mut self) {
+        if !self.handles.is_null() {
+            let _ = self.boot_services.free_pool(self.handles);
+        }
+    }
+}
@@ -1,23 +1,152 @@
-use crate::uefi::{event, Status};
+use core::fmt;
+use crate::uefi::{event, BootServices, Error, Guid, Status};
+use super::Protocol;
 
-opaque! { Mode }
+/// The current text mode and cursor state of an output
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct Mode {
+    pub max_mode: i32,
+    pub mode: i32,
+    pub attribute: i32,
+    pub cursor_column: i32,
+    pub cursor_row: i32,
+    pub cursor_visible: u8
+}
+
+/// A foreground and background colour pair
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(transparent)]
+pub struct Attribute(usize);
+impl Attribute {
+    pub const BLACK: usize = 0x00;
+    pub const BLUE: usize = 0x01;
+    pub const GREEN: usize = 0x02;
+    pub const CYAN: usize = 0x03;
+    pub const RED: usize = 0x04;
+    pub const MAGENTA: usize = 0x05;
+    pub const BROWN: usize = 0x06;
+    pub const LIGHT_GRAY: usize = 0x07;
+    pub const DARK_GRAY: usize = 0x08;
+    pub const LIGHT_BLUE: usize = 0x09;
+    pub const LIGHT_GREEN: usize = 0x0A;
+    pub const LIGHT_CYAN: usize = 0x0B;
+    pub const LIGHT_RED: usize = 0x0C;
+    pub const LIGHT_MAGENTA: usize = 0x0D;
+    pub const YELLOW: usize = 0x0E;
+    pub const WHITE: usize = 0x0F;
+
+    pub const DEFAULT: Self = Self::new(Self::LIGHT_GRAY, Self::BLACK);
+
+    /// Only the first 8 colours may be used for the background
+    pub const fn new(foreground: usize, background: usize) -> Self {
+        Self((foreground & 0xF) | (background & 0x7) << 4)
+    }
+}
+
+/// Identifies keys that have no Unicode character
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(transparent)]
+pub struct ScanCode(pub u16);
+impl ScanCode {
+    pub const NULL: Self = Self(0x00);
+    pub const UP: Self = Self(0x01);
+    pub const DOWN: Self = Self(0x02);
+    pub const RIGHT: Self = Self(0x03);
+    pub const LEFT: Self = Self(0x04);
+    pub const HOME: Self = Self(0x05);
+    pub const END: Self = Self(0x06);
+    pub const INSERT: Self = Self(0x07);
+    pub const DELETE: Self = Self(0x08);
+    pub const PAGE_UP: Self = Self(0x09);
+    pub const PAGE_DOWN: Self = Self(0x0A);
+    pub const F1: Self = Self(0x0B);
+    pub const F2: Self = Self(0x0C);
+    pub const F3: Self = Self(0x0D);
+    pub const F4: Self = Self(0x0E);
+    pub const F5: Self = Self(0x0F);
+    pub const F6: Self = Self(0x10);
+    pub const F7: Self = Self(0x11);
+    pub const F8: Self = Self(0x12);
+    pub const F9: Self = Self(0x13);
+    pub const F10: Self = Self(0x14);
+    pub const ESCAPE: Self = Self(0x17);
+}
 
+/// A key press as reported by the firmware
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(C)]
 pub struct Key {
-    code: u16,
-    utf16_char: u16
+    pub scan_code: ScanCode,
+    /// The UCS-2 character, or 0 if the key has only a scan code
+    pub unicode_char: u16
+}
+impl Key {
+    /// Sort the key into the control keys a line editor cares about and plain characters
+    pub fn decode(self) -> Keystroke {
+        match (self.scan_code, self.unicode_char) {
+            (ScanCode::ESCAPE, _) => Keystroke::Escape,
+            (_, 0x08) => Keystroke::Backspace,
+            (_, 0x09) => Keystroke::Tab,
+            (_, 0x0A) | (_, 0x0D) => Keystroke::Enter,
+            (ScanCode::NULL, c) => match core::char::from_u32(c as u32) {
+                Some(c) if c != '\0' => Keystroke::Char(c),
+                _ => Keystroke::Scan(self.scan_code)
+            },
+            (scan_code, _) => Keystroke::Scan(scan_code)
+        }
+    }
+}
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Keystroke {
+    Char(char),
+    Enter,
+    Backspace,
+    Tab,
+    Escape,
+    /// Any other key without a character, such as the arrow and function keys
+    Scan(ScanCode)
 }
 
 #[repr(C)]
 pub struct Input {
-    pub reset: extern "efiapi" fn(&mut Self, u8) -> Status,
-    pub read_key: extern "efiapi" fn(&mut Self, key: *mut Key) -> Status,
-    pub wait_for_key: event::Event
+    reset: extern "efiapi" fn(&mut Self, u8) -> Status,
+    read_key: extern "efiapi" fn(&mut Self, key: &mut Key) -> Status,
+    wait_for_key: event::Event
+}
+unsafe impl Protocol for Input {
+    const GUID: Guid = Guid::new(0x387477C1, 0x69C7, 0x11D2, [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
 }
 impl Input {
     #[inline]
-    pub fn reset(&mut self, verified: bool) -> Status {
-        (self.reset)(self, verified as _)
+    pub fn reset(&mut self, verified: bool) -> Result<(), Error> {
+        (self.reset)(self, verified as _).into_result()
+    }
+    /// The event signalled when a key is available
+    #[inline]
+    pub fn wait_for_key(&self) -> event::Event {
+        self.wait_for_key
+    }
+    /// Read the next key press if there is one
+    pub fn try_read_key(&mut self) -> Result<Option<Key>, Error> {
+        let mut key = Key {
+            scan_code: ScanCode::NULL,
+            unicode_char: 0
+        };
+        match (self.read_key)(self, &mut key).into_result() {
+            Ok(()) => Ok(Some(key)),
+            Err(Error::NotReady) => Ok(None),
+            Err(error) => Err(error)
+        }
+    }
+    /// Block until a key is pressed
+    pub fn read_key(&mut self, boot_services: &BootServices) -> Result<Key, Error> {
+        loop {
+            if let Some(key) = self.try_read_key()? {
+                return Ok(key)
+            }
+            boot_services.wait_for_event(&[self.wait_for_key])?;
+        }
     }
 }
 #[repr(C)]
@@ -31,15 +160,71 @@ pub struct Output {
     clear_screen: extern "efiapi" fn(&mut Self) -> Status,
     set_cursor_position: extern "efiapi" fn(&mut Self, x: usize, y: usize) -> Status,
     enable_cursor: extern "efiapi" fn(&mut Self, enabled: u8) -> Status,
-    mode: *mut Mode
+    mode: *const Mode
+}
+unsafe impl Protocol for Output {
+    const GUID: Guid = Guid::new(0x387477C2, 0x69C7, 0x11D2, [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
 }
 impl Output {
     #[inline]
-    pub fn reset(&mut self, verified: bool) -> Status {
-        (self.reset)(self, verified as _)
+    pub fn reset(&mut self, verified: bool) -> Result<(), Error> {
+        (self.reset)(self, verified as _).into_result()
+    }
+    #[inline]
+    pub fn print_utf16(&mut self, utf16_string: *const u16) -> Result<(), Error> {
+        (self.print)(self, utf16_string).into_result()
+    }
+    /// The current mode and cursor position
+    #[inline]
+    pub fn mode(&self) -> &Mode {
+        unsafe { &*self.mode }
+    }
+    /// The number of columns and rows of a text mode
+    pub fn query_mode(&mut self, mode: usize) -> Result<(usize, usize), Error> {
+        let (mut columns, mut rows) = (0, 0);
+        (self.query_mode)(self, mode, &mut columns, &mut rows).into_result()?;
+        Ok((columns, rows))
+    }
+    #[inline]
+    pub fn set_attribute(&mut self, attribute: Attribute) -> Result<(), Error> {
+        (self.set_attribute)(self, attribute.0).into_result()
+    }
+    /// Clear the screen to the current background colour and move the cursor to the top left
+    #[inline]
+    pub fn clear_screen(&mut self) -> Result<(), Error> {
+        (self.clear_screen)(self).into_result()
+    }
+    #[inline]
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) -> Result<(), Error> {
+        (self.set_cursor_position)(self, column, row).into_result()
     }
     #[inline]
-    pub fn print_utf16(&mut self, utf16_string: *const u16) -> Status {
-        (self.print)(self, utf16_string)
+    pub fn enable_cursor(&mut self, visible: bool) -> Result<(), Error> {
+        (self.enable_cursor)(self, visible as _).into_result()
+    }
+}
+impl fmt::Write for Output {
+    /// Convert to UCS-2 in chunks, translating `\n` to `\r\n`.
+    /// Characters outside the Basic Multilingual Plane are replaced with U+FFFD.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        const CAPACITY: usize = 128;
+        let mut buffer = [0u16; CAPACITY];
+        let mut len = 0;
+        for c in s.chars() {
+            // Leave room for a carriage return, the character and the null terminator
+            if len + 3 > CAPACITY {
+                buffer[len] = 0;
+                self.print_utf16(buffer.as_ptr()).map_err(|_| fmt::Error)?;
+                len = 0;
+            }
+            if c == '\n' {
+                buffer[len] = b'\r' as u16;
+                len += 1;
+            }
+            buffer[len] = if (c as u32) < 0x10000 { c as u16 } else { 0xFFFD };
+            len += 1;
+        }
+        buffer[len] = 0;
+        self.print_utf16(buffer.as_ptr()).map_err(|_| fmt::Error)
     }
-}
\ No newline at end of file
+}
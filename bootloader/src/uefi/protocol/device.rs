@@ -1,10 +1,438 @@
+use core::fmt;
+use crate::uefi::Guid;
+use super::Protocol;
+
+/// The first node of a device path, followed in memory by the rest of the path up to an end node
 #[repr(C)]
 pub struct Path {
+    kind: u8,
+    sub_type: u8,
+    length: [u8; 2]
+}
+unsafe impl Protocol for Path {
+    const GUID: Guid = Guid::new(0x09576E91, 0x6D3F, 0x11D2, [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
+}
+/// The part of a device path left over after a handle has consumed its prefix
+pub type RemainingPath = Path;
+
+opaque!{ Device }
 
+impl Path {
+    pub const HARDWARE: u8 = 0x01;
+    pub const ACPI: u8 = 0x02;
+    pub const MESSAGING: u8 = 0x03;
+    pub const MEDIA: u8 = 0x04;
+    pub const BBS: u8 = 0x05;
+    pub const END: u8 = 0x7F;
+
+    const END_INSTANCE: u8 = 0x01;
+    const END_ENTIRE: u8 = 0xFF;
+    const HEADER: usize = core::mem::size_of::<Self>();
+
+    /// The decoded nodes of the path, not including the final end node
+    pub fn nodes(&self) -> Nodes<'_> {
+        Nodes(self.raw())
+    }
+    /// Every node up to but not including the final end node, including the header
+    fn raw(&self) -> RawNodes<'_> {
+        RawNodes {
+            next: self as *const Self as *const u8,
+            _path: core::marker::PhantomData
+        }
+    }
+}
+impl fmt::Display for Path {
+    /// Render the path in the UEFI DevicePathToText format, separating instances with commas
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+        for node in self.nodes() {
+            if let Node::EndInstance = node {
+                separator = "";
+                f.write_str(",")?;
+            } else {
+                write!(f, "{}{}", separator, node)?;
+                separator = "/";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the raw bytes of each node of a path
+struct RawNodes<'a> {
+    next: *const u8,
+    _path: core::marker::PhantomData<&'a Path>
+}
+impl<'a> Iterator for RawNodes<'a> {
+    type Item = &'a [u8];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None
+        }
+        // Safe: a path is a sequence of nodes terminated by an end node, each starting with a header
+        let header = unsafe { &*(self.next as *const Path) };
+        let length = u16::from_le_bytes(header.length) as usize;
+        // A node too short to hold its own header would loop forever
+        if header.kind == Path::END && header.sub_type == Path::END_ENTIRE || length < Path::HEADER {
+            self.next = core::ptr::null();
+            return None
+        }
+        let node = unsafe { core::slice::from_raw_parts(self.next, length) };
+        self.next = unsafe { self.next.add(length) };
+        Some(node)
+    }
+}
+
+/// Iterator over the decoded nodes of a path
+pub struct Nodes<'a>(RawNodes<'a>);
+impl<'a> Iterator for Nodes<'a> {
+    type Item = Node<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Node::decode)
+    }
+}
+
+/// The signature identifying the disk a partition belongs to
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Signature {
+    None,
+    Mbr(u32),
+    Gpt(Guid),
+    Unknown(u8)
 }
-#[repr(C)]
-pub struct RemainingPath {
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct HardDrive {
+    /// The partition number, starting from 1
+    pub partition: u32,
+    /// The first logical block of the partition
+    pub start: u64,
+    /// The partition size in logical blocks
+    pub size: u64,
+    pub signature: Signature
 }
 
-opaque!{ Device }
\ No newline at end of file
+/// A UCS-2 string stored unaligned in a node
+#[derive(Copy, Clone)]
+pub struct Text<'a>(&'a [u8]);
+impl<'a> Text<'a> {
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        let units = self.0.chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .take_while(|&unit| unit != 0);
+        core::char::decode_utf16(units).map(|c| c.unwrap_or(core::char::REPLACEMENT_CHARACTER))
+    }
+}
+impl fmt::Display for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.chars().try_for_each(|c| fmt::Write::write_char(f, c))
+    }
+}
+
+/// A single device path node
+#[derive(Copy, Clone)]
+pub enum Node<'a> {
+    Pci { function: u8, device: u8 },
+    MemoryMapped { memory_type: u32, start: u64, end: u64 },
+    /// A vendor defined node of any type
+    Vendor { kind: u8, guid: Guid, data: &'a [u8] },
+    Controller(u32),
+    Acpi { hid: u32, uid: u32 },
+    Scsi { target: u16, lun: u16 },
+    Usb { port: u8, interface: u8 },
+    Mac { address: [u8; 32], interface_type: u8 },
+    Sata { port: u16, multiplier: u16, lun: u16 },
+    Nvme { namespace: u32, eui: [u8; 8] },
+    Sd(u8),
+    Emmc(u8),
+    HardDrive(HardDrive),
+    CdRom { entry: u32, start: u64, size: u64 },
+    FilePath(Text<'a>),
+    MediaProtocol(Guid),
+    FirmwareFile(Guid),
+    FirmwareVolume(Guid),
+    /// Separates the instances of a multi-instance path
+    EndInstance,
+    Unknown { kind: u8, sub_type: u8, data: &'a [u8] }
+}
+impl<'a> Node<'a> {
+    /// Decode a node from its raw bytes, including the header
+    fn decode(node: &'a [u8]) -> Self {
+        let (kind, sub_type, data) = (node[0], node[1], &node[Path::HEADER..]);
+        Self::decode_data(kind, sub_type, data).unwrap_or(Self::Unknown { kind, sub_type, data })
+    }
+    fn decode_data(kind: u8, sub_type: u8, data: &'a [u8]) -> Option<Self> {
+        let u8_at = |offset: usize| data.get(offset).copied();
+        let u16_at = |offset: usize| Some(u16::from_le_bytes(bytes(data, offset)?));
+        let u32_at = |offset: usize| Some(u32::from_le_bytes(bytes(data, offset)?));
+        let u64_at = |offset: usize| Some(u64::from_le_bytes(bytes(data, offset)?));
+        let guid_at = |offset: usize| Some(Guid::from_bytes(bytes(data, offset)?));
+
+        Some(match (kind, sub_type) {
+            (Path::HARDWARE, 0x01) => Self::Pci { function: u8_at(0)?, device: u8_at(1)? },
+            (Path::HARDWARE, 0x03) => Self::MemoryMapped { memory_type: u32_at(0)?, start: u64_at(4)?, end: u64_at(12)? },
+            (Path::HARDWARE, 0x04) | (Path::MESSAGING, 0x0A) | (Path::MEDIA, 0x03) => Self::Vendor { kind, guid: guid_at(0)?, data: &data[16..] },
+            (Path::HARDWARE, 0x05) => Self::Controller(u32_at(0)?),
+            (Path::ACPI, 0x01) => Self::Acpi { hid: u32_at(0)?, uid: u32_at(4)? },
+            (Path::MESSAGING, 0x02) => Self::Scsi { target: u16_at(0)?, lun: u16_at(2)? },
+            (Path::MESSAGING, 0x05) => Self::Usb { port: u8_at(0)?, interface: u8_at(1)? },
+            (Path::MESSAGING, 0x0B) => Self::Mac { address: bytes(data, 0)?, interface_type: u8_at(32)? },
+            (Path::MESSAGING, 0x12) => Self::Sata { port: u16_at(0)?, multiplier: u16_at(2)?, lun: u16_at(4)? },
+            (Path::MESSAGING, 0x17) => Self::Nvme { namespace: u32_at(0)?, eui: bytes(data, 4)? },
+            (Path::MESSAGING, 0x1A) => Self::Sd(u8_at(0)?),
+            (Path::MESSAGING, 0x1D) => Self::Emmc(u8_at(0)?),
+            (Path::MEDIA, 0x01) => Self::HardDrive(HardDrive {
+                partition: u32_at(0)?,
+                start: u64_at(4)?,
+                size: u64_at(12)?,
+                signature: match u8_at(37)? {
+                    0x00 => Signature::None,
+                    0x01 => Signature::Mbr(u32_at(20)?),
+                    0x02 => Signature::Gpt(guid_at(20)?),
+                    other => Signature::Unknown(other)
+                }
+            }),
+            (Path::MEDIA, 0x02) => Self::CdRom { entry: u32_at(0)?, start: u64_at(4)?, size: u64_at(12)? },
+            (Path::MEDIA, 0x04) => Self::FilePath(Text(data)),
+            (Path::MEDIA, 0x05) => Self::MediaProtocol(guid_at(0)?),
+            (Path::MEDIA, 0x06) => Self::FirmwareFile(guid_at(0)?),
+            (Path::MEDIA, 0x07) => Self::FirmwareVolume(guid_at(0)?),
+            (Path::END, Path::END_INSTANCE) => Self::EndInstance,
+            _ => return None
+        })
+    }
+}
+impl fmt::Display for Node<'_> {
+    /// Render the node as UEFI DevicePathToText would
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Pci { function, device } => write!(f, "Pci({:#x},{:#x})", device, function),
+            Self::MemoryMapped { memory_type, start, end } => write!(f, "MemoryMapped({:#x},{:#x},{:#x})", memory_type, start, end),
+            Self::Vendor { kind, guid, data } => {
+                let prefix = match kind {
+                    Path::HARDWARE => "VenHw",
+                    Path::MESSAGING => "VenMsg",
+                    _ => "VenMedia"
+                };
+                write!(f, "{}({}", prefix, guid)?;
+                if !data.is_empty() {
+                    f.write_str(",")?;
+                    hex(f, data)?;
+                }
+                f.write_str(")")
+            },
+            Self::Controller(controller) => write!(f, "Ctrl({:#x})", controller),
+            Self::Acpi { hid, uid } => {
+                // Compressed EISA PNP identifiers have well known names
+                const PNP: u32 = 0x41D0;
+                if hid & 0xFFFF != PNP {
+                    return write!(f, "Acpi({:#010x},{:#x})", hid, uid)
+                }
+                match hid >> 16 {
+                    0x0A03 => write!(f, "PciRoot({:#x})", uid),
+                    0x0A08 => write!(f, "PcieRoot({:#x})", uid),
+                    0x0604 => write!(f, "Floppy({:#x})", uid),
+                    0x0301 => write!(f, "Keyboard({:#x})", uid),
+                    0x0501 => write!(f, "Serial({:#x})", uid),
+                    0x0401 => write!(f, "ParallelPort({:#x})", uid),
+                    id => write!(f, "Acpi(PNP{:04x},{:#x})", id, uid)
+                }
+            },
+            Self::Scsi { target, lun } => write!(f, "Scsi({:#x},{:#x})", target, lun),
+            Self::Usb { port, interface } => write!(f, "USB({:#x},{:#x})", port, interface),
+            Self::Mac { address, interface_type } => {
+                // Ethernet addresses only use the first 6 bytes of the field
+                let length = if interface_type <= 1 { 6 } else { address.len() };
+                f.write_str("MAC(")?;
+                hex(f, &address[..length])?;
+                write!(f, ",{:#x})", interface_type)
+            },
+            Self::Sata { port, multiplier, lun } => write!(f, "Sata({:#x},{:#x},{:#x})", port, multiplier, lun),
+            Self::Nvme { namespace, eui } => write!(
+                f,
+                "NVMe({:#x},{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x})",
+                namespace, eui[7], eui[6], eui[5], eui[4], eui[3], eui[2], eui[1], eui[0]
+            ),
+            Self::Sd(slot) => write!(f, "SD({:#x})", slot),
+            Self::Emmc(slot) => write!(f, "eMMC({:#x})", slot),
+            Self::HardDrive(HardDrive { partition, start, size, signature }) => {
+                match signature {
+                    Signature::Mbr(signature) => write!(f, "HD({},MBR,{:#010x},", partition, signature)?,
+                    Signature::Gpt(guid) => write!(f, "HD({},GPT,{},", partition, guid)?,
+                    Signature::None => write!(f, "HD({},0,0,", partition)?,
+                    Signature::Unknown(kind) => write!(f, "HD({},{},0,", partition, kind)?
+                }
+                write!(f, "{:#x},{:#x})", start, size)
+            },
+            Self::CdRom { entry, start, size } => write!(f, "CDROM({:#x},{:#x},{:#x})", entry, start, size),
+            Self::FilePath(path) => write!(f, "{}", path),
+            Self::MediaProtocol(guid) => write!(f, "Media({})", guid),
+            Self::FirmwareFile(guid) => write!(f, "FvFile({})", guid),
+            Self::FirmwareVolume(guid) => write!(f, "Fv({})", guid),
+            Self::EndInstance => f.write_str(","),
+            Self::Unknown { kind, sub_type, data } => {
+                match kind {
+                    Path::HARDWARE => write!(f, "HardwarePath({}", sub_type)?,
+                    Path::ACPI => write!(f, "AcpiPath({}", sub_type)?,
+                    Path::MESSAGING => write!(f, "Msg({}", sub_type)?,
+                    Path::MEDIA => write!(f, "MediaPath({}", sub_type)?,
+                    Path::BBS => write!(f, "BbsPath({}", sub_type)?,
+                    _ => write!(f, "Path({},{}", kind, sub_type)?
+                }
+                if !data.is_empty() {
+                    f.write_str(",")?;
+                    hex(f, data)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Copy `N` bytes out of a node, which is not necessarily aligned
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(data.get(offset..offset + N)?);
+    Some(bytes)
+}
+fn hex(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    data.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2b7c2f4a-5c1e-4d2a-9b1e-6f3d2c118a07 as stored in a node
+    const DISK: [u8; 16] = [0x4A, 0x2F, 0x7C, 0x2B, 0x1E, 0x5C, 0x2A, 0x4D, 0x9B, 0x1E, 0x6F, 0x3D, 0x2C, 0x11, 0x8A, 0x07];
+
+    fn node(kind: u8, sub_type: u8, data: &[u8]) -> Vec<u8> {
+        let length = (Path::HEADER + data.len()) as u16;
+        let mut node = vec![kind, sub_type];
+        node.extend_from_slice(&length.to_le_bytes());
+        node.extend_from_slice(data);
+        node
+    }
+    /// The nodes one after another, followed by the end node
+    fn path(nodes: &[Vec<u8>]) -> Vec<u8> {
+        let mut path = nodes.concat();
+        path.extend(node(Path::END, Path::END_ENTIRE, &[]));
+        path
+    }
+    fn render(path: &[u8]) -> String {
+        // Safe: the tests only build whole paths ending in an end node
+        unsafe { &*(path.as_ptr() as *const Path) }.to_string()
+    }
+    fn pci_root() -> Vec<u8> {
+        node(Path::ACPI, 0x01, &[0xD0, 0x41, 0x03, 0x0A, 0, 0, 0, 0])
+    }
+    fn file(name: &str) -> Vec<u8> {
+        let data: Vec<u8> = name.encode_utf16().chain(Some(0)).flat_map(u16::to_le_bytes).collect();
+        node(Path::MEDIA, 0x04, &data)
+    }
+    fn hard_drive(signature: &[u8], format: u8, signature_type: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0x800u64.to_le_bytes());
+        data.extend_from_slice(&0x100000u64.to_le_bytes());
+        let mut field = [0; 16];
+        field[..signature.len()].copy_from_slice(signature);
+        data.extend_from_slice(&field);
+        data.extend_from_slice(&[format, signature_type]);
+        node(Path::MEDIA, 0x01, &data)
+    }
+
+    #[test]
+    fn boot_file() {
+        let path = path(&[
+            pci_root(),
+            node(Path::HARDWARE, 0x01, &[0x02, 0x1F]),
+            node(Path::MESSAGING, 0x12, &[0, 0, 0xFF, 0xFF, 0, 0]),
+            hard_drive(&DISK, 0x02, 0x02),
+            file("\\EFI\\BOOT\\BOOTX64.EFI")
+        ]);
+        assert_eq!(
+            render(&path),
+            "PciRoot(0x0)/Pci(0x1f,0x2)/Sata(0x0,0xffff,0x0)/HD(1,GPT,2b7c2f4a-5c1e-4d2a-9b1e-6f3d2c118a07,0x800,0x100000)/\\EFI\\BOOT\\BOOTX64.EFI"
+        );
+    }
+    #[test]
+    fn decode() {
+        let path = path(&[hard_drive(&0x12345678u32.to_le_bytes(), 0x01, 0x01), file("kernel")]);
+        // Safe: built as a whole path
+        let path = unsafe { &*(path.as_ptr() as *const Path) };
+        let mut nodes = path.nodes();
+        match nodes.next() {
+            Some(Node::HardDrive(partition)) => assert_eq!(partition, HardDrive {
+                partition: 1,
+                start: 0x800,
+                size: 0x100000,
+                signature: Signature::Mbr(0x12345678)
+            }),
+            _ => panic!("expected a hard drive node")
+        }
+        match nodes.next() {
+            Some(Node::FilePath(name)) => assert!(name.chars().eq("kernel".chars())),
+            _ => panic!("expected a file path node")
+        }
+        assert!(nodes.next().is_none());
+    }
+    #[test]
+    fn instances() {
+        let path = path(&[
+            pci_root(),
+            node(Path::HARDWARE, 0x01, &[0x00, 0x02]),
+            node(Path::END, Path::END_INSTANCE, &[]),
+            pci_root(),
+            node(Path::HARDWARE, 0x01, &[0x00, 0x03])
+        ]);
+        assert_eq!(render(&path), "PciRoot(0x0)/Pci(0x2,0x0),PciRoot(0x0)/Pci(0x3,0x0)");
+    }
+    #[test]
+    fn messaging() {
+        let mut mac = [0; 33];
+        mac[..6].copy_from_slice(&[0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+        mac[32] = 0x01;
+        let mut nvme = 1u32.to_le_bytes().to_vec();
+        nvme.extend_from_slice(&[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+        let path = path(&[
+            node(Path::MESSAGING, 0x0B, &mac),
+            node(Path::MESSAGING, 0x17, &nvme),
+            node(Path::MESSAGING, 0x05, &[0x03, 0x00]),
+            node(Path::MESSAGING, 0x02, &[0x01, 0x00, 0x00, 0x00])
+        ]);
+        assert_eq!(render(&path), "MAC(525400123456,0x1)/NVMe(0x1,01-02-03-04-05-06-07-08)/USB(0x3,0x0)/Scsi(0x1,0x0)");
+    }
+    #[test]
+    fn acpi() {
+        let path = path(&[
+            node(Path::ACPI, 0x01, &[0xD0, 0x41, 0x01, 0x05, 1, 0, 0, 0]),
+            node(Path::ACPI, 0x01, &[0xD0, 0x41, 0x34, 0x12, 0, 0, 0, 0]),
+            node(Path::ACPI, 0x01, &[0x78, 0x56, 0x34, 0x12, 2, 0, 0, 0])
+        ]);
+        assert_eq!(render(&path), "Serial(0x1)/Acpi(PNP1234,0x0)/Acpi(0x12345678,0x2)");
+    }
+    #[test]
+    fn vendor() {
+        let mut data = DISK.to_vec();
+        data.extend_from_slice(&[0xAB, 0xCD]);
+        let path = path(&[node(Path::HARDWARE, 0x04, &data), node(Path::MEDIA, 0x03, &DISK)]);
+        assert_eq!(
+            render(&path),
+            "VenHw(2b7c2f4a-5c1e-4d2a-9b1e-6f3d2c118a07,abcd)/VenMedia(2b7c2f4a-5c1e-4d2a-9b1e-6f3d2c118a07)"
+        );
+    }
+    #[test]
+    fn unknown() {
+        // Unsupported sub-types and known nodes too short for their fields are rendered generically
+        let path = path(&[node(Path::MESSAGING, 0x99, &[0xAB]), node(Path::HARDWARE, 0x01, &[0x02]), node(0x42, 0x01, &[])]);
+        assert_eq!(render(&path), "Msg(153,ab)/HardwarePath(1,02)/Path(66,1)");
+    }
+    #[test]
+    fn short_node() {
+        // A node claiming to be shorter than its header ends the path rather than looping forever
+        let mut path = path(&[pci_root()]);
+        path.splice(0..0, [Path::HARDWARE, 0x01, 0x02, 0x00]);
+        assert_eq!(render(&path), "");
+    }
+}
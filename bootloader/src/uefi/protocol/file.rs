@@ -0,0 +1,115 @@
+use crate::{void, uefi::{self, Error, Guid, Status, Time}};
+use super::Protocol;
+
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct Mode(u64);
+impl Mode {
+    pub const READ: Self = Self(0x1);
+    pub const WRITE: Self = Self(0x2 | 0x1);
+    pub const CREATE: Self = Self(0x8000000000000000 | 0x2 | 0x1);
+}
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct Attributes(u64);
+impl Attributes {
+    pub const NONE: Self = Self(0x0);
+    pub const READ_ONLY: Self = Self(0x1);
+    pub const HIDDEN: Self = Self(0x2);
+    pub const SYSTEM: Self = Self(0x4);
+    pub const DIRECTORY: Self = Self(0x10);
+    pub const ARCHIVE: Self = Self(0x20);
+
+    pub fn directory(self) -> bool {
+        self.0 & Self::DIRECTORY.0 != 0
+    }
+}
+
+#[repr(C)]
+pub struct SimpleFileSystem {
+    pub revision: u64,
+    open_volume: extern "efiapi" fn(&mut Self, root: &mut *mut File) -> Status
+}
+unsafe impl Protocol for SimpleFileSystem {
+    const GUID: Guid = Guid::new(0x964E5B22, 0x6459, 0x11D2, [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
+}
+impl SimpleFileSystem {
+    /// Open the root directory of the volume
+    pub fn open_volume(&mut self) -> Result<&'static mut File, Error> {
+        let mut root = core::ptr::null_mut();
+        (self.open_volume)(self, &mut root).into_result()?;
+        unsafe { root.as_mut() }.ok_or(Error::DeviceError)
+    }
+}
+
+#[repr(C)]
+pub struct File {
+    pub revision: u64,
+    open: extern "efiapi" fn(&mut Self, new: &mut *mut File, file_name: *const u16, mode: Mode, attributes: Attributes) -> Status,
+    close: extern "efiapi" fn(&mut Self) -> Status,
+    delete: extern "efiapi" fn(&mut Self) -> Status,
+    read: extern "efiapi" fn(&mut Self, buffer_size: &mut usize, buffer: *mut void) -> Status,
+    write: extern "efiapi" fn(&mut Self, buffer_size: &mut usize, buffer: *const void) -> Status,
+    get_position: extern "efiapi" fn(&mut Self, position: &mut u64) -> Status,
+    set_position: extern "efiapi" fn(&mut Self, position: u64) -> Status,
+    get_info: extern "efiapi" fn(&mut Self, info_type: &Guid, buffer_size: &mut usize, buffer: *mut void) -> Status,
+    set_info: extern "efiapi" fn(&mut Self, info_type: &Guid, buffer_size: usize, buffer: *const void) -> Status,
+    flush: extern "efiapi" fn(&mut Self) -> Status
+}
+impl File {
+    /// The longest path, in UTF-16 code units, that can be opened
+    pub const MAX_PATH: usize = 255;
+
+    /// Open a file relative to this directory.
+    /// The path uses `\` as a separator and is converted to a null-terminated UCS-2 string.
+    pub fn open(&mut self, path: &str, mode: Mode) -> Result<&'static mut File, Error> {
+        let mut utf16_path = [0u16; Self::MAX_PATH + 1];
+        uefi::encode_ucs2(path, &mut utf16_path)?;
+
+        let mut file = core::ptr::null_mut();
+        (self.open)(self, &mut file, utf16_path.as_ptr(), mode, Attributes::NONE).into_result()?;
+        unsafe { file.as_mut() }.ok_or(Error::DeviceError)
+    }
+    #[inline]
+    pub fn close(&mut self) -> Result<(), Error> {
+        (self.close)(self).into_result()
+    }
+    /// Read from the current position into `buffer`, returning the number of bytes read
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut size = buffer.len();
+        (self.read)(self, &mut size, buffer.as_mut_ptr() as _).into_result()?;
+        Ok(size)
+    }
+    pub fn position(&mut self) -> Result<u64, Error> {
+        let mut position = 0;
+        (self.get_position)(self, &mut position).into_result()?;
+        Ok(position)
+    }
+    #[inline]
+    pub fn set_position(&mut self, position: u64) -> Result<(), Error> {
+        (self.set_position)(self, position).into_result()
+    }
+    pub fn info(&mut self) -> Result<Info, Error> {
+        // Safe: Info is plain old data
+        let mut info: Info = unsafe { core::mem::zeroed() };
+        let mut size = core::mem::size_of::<Info>();
+        (self.get_info)(self, &Info::GUID, &mut size, &mut info as *mut Info as _).into_result()?;
+        Ok(info)
+    }
+}
+
+#[repr(C)]
+pub struct Info {
+    /// The size of this structure including the null-terminated file name
+    pub size: u64,
+    pub file_size: u64,
+    pub physical_size: u64,
+    pub create_time: Time,
+    pub access_time: Time,
+    pub modification_time: Time,
+    pub attributes: Attributes,
+    pub file_name: [u16; File::MAX_PATH + 1]
+}
+impl Info {
+    pub const GUID: Guid = Guid::new(0x09576E92, 0x6D3F, 0x11D2, [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
+}
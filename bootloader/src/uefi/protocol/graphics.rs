@@ -0,0 +1,80 @@
+use crate::{void, uefi::{BootServices, Error, Guid, Status}};
+use super::Protocol;
+
+/// Firmware may report formats newer than the specification this was written against
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(transparent)]
+pub struct PixelFormat(u32);
+impl PixelFormat {
+    /// 8 bits per channel, red in the lowest byte
+    pub const RGBX: Self = Self(0);
+    /// 8 bits per channel, blue in the lowest byte
+    pub const BGRX: Self = Self(1);
+    /// Described by `ModeInformation::mask`
+    pub const BITMASK: Self = Self(2);
+    /// No linear framebuffer, only Blt is supported
+    pub const BLT_ONLY: Self = Self(3);
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct PixelBitmask {
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+    pub reserved: u32
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ModeInformation {
+    pub version: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub mask: PixelBitmask,
+    /// Pixels per scanline, which may be more than `width`
+    pub stride: u32
+}
+
+#[repr(C)]
+pub struct Mode {
+    pub max_mode: u32,
+    pub mode: u32,
+    pub info: *const ModeInformation,
+    pub info_size: usize,
+    pub framebuffer_base: u64,
+    pub framebuffer_size: usize
+}
+
+#[repr(C)]
+pub struct GraphicsOutput {
+    query_mode: extern "efiapi" fn(&mut Self, mode: u32, info_size: &mut usize, info: &mut *mut ModeInformation) -> Status,
+    set_mode: extern "efiapi" fn(&mut Self, mode: u32) -> Status,
+    blt: extern "efiapi" fn(&mut Self, buffer: *mut void, operation: u32, source_x: usize, source_y: usize, destination_x: usize, destination_y: usize, width: usize, height: usize, delta: usize) -> Status,
+    mode: *const Mode
+}
+unsafe impl Protocol for GraphicsOutput {
+    const GUID: Guid = Guid::new(0x9042A9DE, 0x23DC, 0x4A38, [0x96, 0xFB, 0x7A, 0xDE, 0xD0, 0x80, 0x51, 0x6A]);
+}
+impl GraphicsOutput {
+    /// The current mode and framebuffer
+    #[inline]
+    pub fn mode(&self) -> &Mode {
+        unsafe { &*self.mode }
+    }
+    /// Describe a mode, `mode` must be less than `Mode::max_mode`
+    pub fn query_mode(&mut self, boot_services: &BootServices, mode: u32) -> Result<ModeInformation, Error> {
+        let mut info_size = 0;
+        let mut info = core::ptr::null_mut();
+        (self.query_mode)(self, mode, &mut info_size, &mut info).into_result()?;
+        // The firmware allocates the information from pool
+        let mode_information = unsafe { *info };
+        let _ = boot_services.free_pool(info);
+        Ok(mode_information)
+    }
+    #[inline]
+    pub fn set_mode(&mut self, mode: u32) -> Result<(), Error> {
+        (self.set_mode)(self, mode).into_result()
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{void, uefi::{Guid, ImageHandle, Status, SystemTable, mem}};
+use super::{Handle, Protocol, device};
+
+#[repr(C)]
+pub struct LoadedImage {
+    pub revision: u32,
+    pub parent: ImageHandle,
+    pub system_table: *mut SystemTable,
+    /// The handle of the device the image was loaded from
+    pub device: Handle,
+    pub file_path: *const device::Path,
+    _reserved: *const void,
+    pub load_options_size: u32,
+    pub load_options: *const void,
+    pub image_base: *mut void,
+    pub image_size: u64,
+    pub image_code_type: mem::MemoryType,
+    pub image_data_type: mem::MemoryType,
+    pub unload: Option<extern "efiapi" fn(ImageHandle) -> Status>
+}
+unsafe impl Protocol for LoadedImage {
+    const GUID: Guid = Guid::new(0x5B1B31A1, 0x9562, 0x11D2, [0x8E, 0x3F, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B]);
+}
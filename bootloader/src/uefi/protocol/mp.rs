@@ -0,0 +1,100 @@
+use crate::{void, uefi::{Error, Guid, Status, event::Event}};
+use super::Protocol;
+
+/// EFI_MP_SERVICES_PROTOCOL from the Platform Initialization specification
+#[repr(C)]
+pub struct MpServices {
+    get_number_of_processors: extern "efiapi" fn(&mut Self, count: &mut usize, enabled: &mut usize) -> Status,
+    get_processor_info: extern "efiapi" fn(&mut Self, processor: usize, info: &mut ProcessorInformation) -> Status,
+    startup_all_aps: extern "efiapi" fn(&mut Self, procedure: ApProcedure, single_thread: bool, wait: Event, timeout: usize, argument: *mut void, failed: *mut *mut usize) -> Status,
+    startup_this_ap: extern "efiapi" fn(&mut Self, procedure: ApProcedure, processor: usize, wait: Event, timeout: usize, argument: *mut void, finished: *mut bool) -> Status,
+    switch_bsp: extern "efiapi" fn(&mut Self, processor: usize, enable_old_bsp: bool) -> Status,
+    enable_disable_ap: extern "efiapi" fn(&mut Self, processor: usize, enable: bool, health: Option<&u32>) -> Status,
+    who_am_i: extern "efiapi" fn(&mut Self, processor: &mut usize) -> Status
+}
+/// Code run on an application processor
+pub type ApProcedure = extern "efiapi" fn(argument: *mut void);
+
+unsafe impl Protocol for MpServices {
+    const GUID: Guid = Guid::new(0x3FDDA605, 0xA76E, 0x4F46, [0xAD, 0x29, 0x12, 0xF4, 0x53, 0x1B, 0x3D, 0x08]);
+}
+impl MpServices {
+    /// The number of logical processors, and how many of them are enabled
+    pub fn count(&mut self) -> Result<(usize, usize), Error> {
+        let mut count = 0;
+        let mut enabled = 0;
+        (self.get_number_of_processors)(self, &mut count, &mut enabled).into_result()?;
+        Ok((count, enabled))
+    }
+    /// Describe the processor with the firmware's index `processor`, which is less than the count
+    pub fn info(&mut self, processor: usize) -> Result<ProcessorInformation, Error> {
+        let mut info = ProcessorInformation::default();
+        (self.get_processor_info)(self, processor, &mut info).into_result()?;
+        Ok(info)
+    }
+    /// The firmware's index for the processor running the caller
+    pub fn who_am_i(&mut self) -> Result<usize, Error> {
+        let mut processor = 0;
+        (self.who_am_i)(self, &mut processor).into_result()?;
+        Ok(processor)
+    }
+}
+
+/// EFI_PROCESSOR_INFORMATION
+#[derive(Copy, Clone, Default, Debug)]
+#[repr(C)]
+pub struct ProcessorInformation {
+    /// The local APIC ID on x86
+    pub processor_id: u64,
+    pub status: ProcessorStatus,
+    pub location: Location,
+    /// Only filled in when the extended topology is requested, which the bootloader does not do
+    _extended: [u32; 6]
+}
+
+impl From<&ProcessorInformation> for bootinfo::Processor {
+    fn from(info: &ProcessorInformation) -> Self {
+        // The flags are defined to match
+        Self {
+            apic_id: info.processor_id as u32,
+            flags: info.status.0 & (ProcessorStatus::BSP | ProcessorStatus::ENABLED | ProcessorStatus::HEALTHY),
+            package: info.location.package,
+            core: info.location.core,
+            thread: info.location.thread
+        }
+    }
+}
+
+/// Flags describing the state of a processor
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[repr(transparent)]
+pub struct ProcessorStatus(pub u32);
+impl ProcessorStatus {
+    /// The bootstrap processor, which runs the bootloader
+    pub const BSP: u32 = 1 << 0;
+    pub const ENABLED: u32 = 1 << 1;
+    /// Passed its built-in self test
+    pub const HEALTHY: u32 = 1 << 2;
+
+    #[inline]
+    pub fn bsp(self) -> bool {
+        self.0 & Self::BSP != 0
+    }
+    #[inline]
+    pub fn enabled(self) -> bool {
+        self.0 & Self::ENABLED != 0
+    }
+    #[inline]
+    pub fn healthy(self) -> bool {
+        self.0 & Self::HEALTHY != 0
+    }
+}
+
+/// EFI_CPU_PHYSICAL_LOCATION
+#[derive(Copy, Clone, Default, Debug)]
+#[repr(C)]
+pub struct Location {
+    pub package: u32,
+    pub core: u32,
+    pub thread: u32
+}
@@ -0,0 +1,18 @@
+use crate::uefi::{Error, Guid, Status};
+use super::Protocol;
+
+/// EFI_RNG_PROTOCOL
+#[repr(C)]
+pub struct Rng {
+    get_info: extern "efiapi" fn(&mut Self, list_size: &mut usize, list: *mut Guid) -> Status,
+    get_rng: extern "efiapi" fn(&mut Self, algorithm: Option<&Guid>, length: usize, value: *mut u8) -> Status
+}
+unsafe impl Protocol for Rng {
+    const GUID: Guid = Guid::new(0x3152BCA5, 0xEADE, 0x433D, [0x86, 0x2E, 0xC0, 0x1C, 0xDC, 0x29, 0x1F, 0x44]);
+}
+impl Rng {
+    /// Fill `buffer` using the firmware's default algorithm
+    pub fn fill(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        (self.get_rng)(self, None, buffer.len(), buffer.as_mut_ptr()).into_result()
+    }
+}
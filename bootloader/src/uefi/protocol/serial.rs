@@ -0,0 +1,73 @@
+use core::fmt;
+use crate::{void, uefi::{Error, Guid, Status}};
+use super::Protocol;
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct Mode {
+    pub control_mask: u32,
+    /// Read timeout in microseconds
+    pub timeout: u32,
+    pub baud_rate: u64,
+    pub receive_fifo_depth: u32,
+    pub data_bits: u32,
+    pub parity: u32,
+    pub stop_bits: u32
+}
+
+/// EFI_SERIAL_IO_PROTOCOL, a byte stream over a serial port owned by the firmware
+#[repr(C)]
+pub struct SerialIo {
+    pub revision: u32,
+    reset: extern "efiapi" fn(&mut Self) -> Status,
+    set_attributes: extern "efiapi" fn(&mut Self, baud_rate: u64, receive_fifo_depth: u32, timeout: u32, parity: u32, data_bits: u8, stop_bits: u32) -> Status,
+    set_control: extern "efiapi" fn(&mut Self, control: u32) -> Status,
+    get_control: extern "efiapi" fn(&mut Self, control: &mut u32) -> Status,
+    write: extern "efiapi" fn(&mut Self, size: &mut usize, buffer: *const void) -> Status,
+    read: extern "efiapi" fn(&mut Self, size: &mut usize, buffer: *mut void) -> Status,
+    mode: *const Mode
+}
+unsafe impl Protocol for SerialIo {
+    const GUID: Guid = Guid::new(0xBB25CF6F, 0xF1D4, 0x11D2, [0x9A, 0x0C, 0x00, 0x90, 0x27, 0x3F, 0xC1, 0xFD]);
+}
+impl SerialIo {
+    #[inline]
+    pub fn reset(&mut self) -> Result<(), Error> {
+        (self.reset)(self).into_result()
+    }
+    #[inline]
+    pub fn mode(&self) -> &Mode {
+        unsafe { &*self.mode }
+    }
+    /// Write as much of `data` as the port accepts before timing out, returning the number of bytes written
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut size = data.len();
+        match (self.write)(self, &mut size, data.as_ptr() as _).into_result() {
+            Ok(()) | Err(Error::Timeout) => Ok(size),
+            Err(error) => Err(error)
+        }
+    }
+    /// Write all of `data`, retrying after timeouts as long as some of it is accepted each time.
+    /// A port that accepts nothing before timing out, such as one held back by flow control, fails with `Error::Timeout`.
+    pub fn write_all(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        while !data.is_empty() {
+            match self.write(data)? {
+                0 => return Err(Error::Timeout),
+                written => data = &data[written..]
+            }
+        }
+        Ok(())
+    }
+}
+impl fmt::Write for SerialIo {
+    /// Translates `\n` to `\r\n`
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.write_all(b"\r\n").map_err(|_| fmt::Error)?;
+            }
+            self.write_all(line.as_bytes()).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
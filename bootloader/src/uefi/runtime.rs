@@ -0,0 +1,147 @@
+use crate::{void, uefi::{self, Error, Guid, Status, TableHeader, Time, mem}};
+
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct ResetType(u32);
+impl ResetType {
+    pub const COLD: Self = Self(0);
+    pub const WARM: Self = Self(1);
+    pub const SHUTDOWN: Self = Self(2);
+    pub const PLATFORM_SPECIFIC: Self = Self(3);
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct TimeCapabilities {
+    /// Clock resolution in counts per second
+    pub resolution: u32,
+    /// Accuracy in parts per million multiplied by 1,000,000
+    pub accuracy: u32,
+    /// Setting the time resets the time below the resolution
+    pub sets_to_zero: bool
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct VariableAttributes(u32);
+impl VariableAttributes {
+    pub const NON_VOLATILE: Self = Self(0x1);
+    pub const BOOTSERVICE_ACCESS: Self = Self(0x2);
+    pub const RUNTIME_ACCESS: Self = Self(0x4);
+    pub const HARDWARE_ERROR_RECORD: Self = Self(0x8);
+    pub const TIME_BASED_AUTHENTICATED_WRITE_ACCESS: Self = Self(0x20);
+    pub const APPEND_WRITE: Self = Self(0x40);
+
+    #[inline]
+    pub fn contains(self, attributes: Self) -> bool {
+        self.0 & attributes.0 == attributes.0
+    }
+}
+impl core::ops::BitOr for VariableAttributes {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct VariableInfo {
+    pub max_storage: u64,
+    pub remaining_storage: u64,
+    pub max_variable_size: u64
+}
+
+/// The longest variable name, in UTF-16 code units, that the wrappers accept
+pub const MAX_VARIABLE_NAME: usize = 127;
+
+#[repr(C)]
+pub struct RuntimeServices {
+    header: TableHeader,
+
+    get_time: extern "efiapi" fn(time: &mut Time, capabilities: Option<&mut TimeCapabilities>) -> Status,
+    set_time: extern "efiapi" fn(time: &Time) -> Status,
+    get_wakeup_time: extern "efiapi" fn(enabled: &mut bool, pending: &mut bool, time: &mut Time) -> Status,
+    set_wakeup_time: extern "efiapi" fn(enable: bool, time: Option<&Time>) -> Status,
+
+    set_virtual_address_map: extern "efiapi" fn(map_size: usize, descriptor_size: usize, descriptor_version: u32, virtual_map: *mut mem::MemoryDescriptor) -> Status,
+    convert_pointer: extern "efiapi" fn(debug_disposition: usize, address: &mut *const void) -> Status,
+
+    get_variable: extern "efiapi" fn(name: *const u16, vendor: &Guid, attributes: Option<&mut VariableAttributes>, data_size: &mut usize, data: *mut void) -> Status,
+    get_next_variable_name: extern "efiapi" fn(name_size: &mut usize, name: *mut u16, vendor: &mut Guid) -> Status,
+    set_variable: extern "efiapi" fn(name: *const u16, vendor: &Guid, attributes: VariableAttributes, data_size: usize, data: *const void) -> Status,
+
+    get_next_high_monotonic_count: extern "efiapi" fn(high_count: &mut u32) -> Status,
+    reset_system: extern "efiapi" fn(ResetType, Status, data_size: usize, data: *const void) -> !,
+
+    update_capsule: extern "efiapi" fn() -> Status,
+    query_capsule_capabilities: extern "efiapi" fn() -> Status,
+    query_variable_info: extern "efiapi" fn(attributes: VariableAttributes, max_storage: &mut u64, remaining_storage: &mut u64, max_variable_size: &mut u64) -> Status
+}
+impl RuntimeServices {
+    pub fn time(&self) -> Result<(Time, TimeCapabilities), Error> {
+        // Safe: Time and TimeCapabilities are plain old data
+        let mut time = unsafe { core::mem::zeroed() };
+        let mut capabilities = unsafe { core::mem::zeroed() };
+        (self.get_time)(&mut time, Some(&mut capabilities)).into_result()?;
+        Ok((time, capabilities))
+    }
+    #[inline]
+    pub fn set_time(&self, time: &Time) -> Result<(), Error> {
+        (self.set_time)(time).into_result()
+    }
+    /// Read a variable into `data`, returning its attributes and size.
+    /// Fails with `BufferTooSmall` if `data` cannot hold the variable.
+    pub fn variable(&self, name: &str, vendor: &Guid, data: &mut [u8]) -> Result<(VariableAttributes, usize), Error> {
+        let mut utf16_name = [0u16; MAX_VARIABLE_NAME + 1];
+        uefi::encode_ucs2(name, &mut utf16_name)?;
+        let mut attributes = VariableAttributes(0);
+        let mut size = data.len();
+        (self.get_variable)(utf16_name.as_ptr(), vendor, Some(&mut attributes), &mut size, data.as_mut_ptr() as _).into_result()?;
+        Ok((attributes, size))
+    }
+    /// Create, replace or, with empty `data`, delete a variable
+    pub fn set_variable(&self, name: &str, vendor: &Guid, attributes: VariableAttributes, data: &[u8]) -> Result<(), Error> {
+        let mut utf16_name = [0u16; MAX_VARIABLE_NAME + 1];
+        uefi::encode_ucs2(name, &mut utf16_name)?;
+        (self.set_variable)(utf16_name.as_ptr(), vendor, attributes, data.len(), data.as_ptr() as _).into_result()
+    }
+    /// Advance `name` and `vendor` to the next variable.
+    /// Start with an empty null-terminated name, `NotFound` marks the end of the variables.
+    pub fn next_variable_name(&self, name: &mut [u16], vendor: &mut Guid) -> Result<(), Error> {
+        let mut size = name.len() * core::mem::size_of::<u16>();
+        (self.get_next_variable_name)(&mut size, name.as_mut_ptr(), vendor).into_result()
+    }
+    pub fn query_variable_info(&self, attributes: VariableAttributes) -> Result<VariableInfo, Error> {
+        let mut info = VariableInfo {
+            max_storage: 0,
+            remaining_storage: 0,
+            max_variable_size: 0
+        };
+        (self.query_variable_info)(attributes, &mut info.max_storage, &mut info.remaining_storage, &mut info.max_variable_size).into_result()?;
+        Ok(info)
+    }
+    pub fn next_high_monotonic_count(&self) -> Result<u32, Error> {
+        let mut count = 0;
+        (self.get_next_high_monotonic_count)(&mut count).into_result()?;
+        Ok(count)
+    }
+    #[inline]
+    pub fn reset_system(&self, reset_type: ResetType, status: Status) -> ! {
+        (self.reset_system)(reset_type, status, 0, core::ptr::null())
+    }
+    /// Switch runtime services to the virtual addresses set in the `virtual_start` of each runtime descriptor.
+    /// # Safety
+    /// Boot services must have exited, this may only be called once and
+    /// runtime services must only be called through the new mappings afterwards.
+    pub unsafe fn set_virtual_address_map(&self, memory_map: &mut mem::FinalMemoryMap) -> Result<(), Error> {
+        (self.set_virtual_address_map)(memory_map.total_size, memory_map.descriptor_size, memory_map.version, memory_map.descriptors).into_result()
+    }
+    /// Convert a physical pointer into its virtual address during a virtual address change event
+    /// # Safety
+    /// Must be called from a `VIRTUAL_ADDRESS_CHANGE` event notification
+    pub unsafe fn convert_pointer<T>(&self, pointer: *const T) -> Result<*const T, Error> {
+        let mut address = pointer as *const void;
+        (self.convert_pointer)(0, &mut address).into_result()?;
+        Ok(address as _)
+    }
+}
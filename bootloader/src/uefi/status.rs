@@ -0,0 +1,158 @@
+use core::fmt;
+
+/// An EFI_STATUS code
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Status(pub usize);
+impl Status {
+    /// Set for all error codes, clear for success and warnings
+    pub const ERROR_BIT: usize = isize::MIN as usize;
+
+    pub const SUCCESS: Self = Self(0);
+
+    pub const WARN_UNKNOWN_GLYPH: Self = Self(1);
+    pub const WARN_DELETE_FAILURE: Self = Self(2);
+    pub const WARN_WRITE_FAILURE: Self = Self(3);
+    pub const WARN_BUFFER_TOO_SMALL: Self = Self(4);
+    pub const WARN_STALE_DATA: Self = Self(5);
+    pub const WARN_FILE_SYSTEM: Self = Self(6);
+    pub const WARN_RESET_REQUIRED: Self = Self(7);
+
+    #[inline]
+    pub fn is_error(self) -> bool {
+        self.0 & Self::ERROR_BIT != 0
+    }
+    #[inline]
+    pub fn is_warning(self) -> bool {
+        !self.is_error() && self != Self::SUCCESS
+    }
+    /// Success and warnings are `Ok`, error codes are `Err`
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.is_error() {
+            Err(Error::from_status(self))
+        } else {
+            Ok(())
+        }
+    }
+    /// The name of the code in the UEFI specification
+    pub fn name(self) -> Option<&'static str> {
+        if self.is_error() {
+            return Error::from_status(self).name()
+        }
+        Some(match self {
+            Self::SUCCESS => "EFI_SUCCESS",
+            Self::WARN_UNKNOWN_GLYPH => "EFI_WARN_UNKNOWN_GLYPH",
+            Self::WARN_DELETE_FAILURE => "EFI_WARN_DELETE_FAILURE",
+            Self::WARN_WRITE_FAILURE => "EFI_WARN_WRITE_FAILURE",
+            Self::WARN_BUFFER_TOO_SMALL => "EFI_WARN_BUFFER_TOO_SMALL",
+            Self::WARN_STALE_DATA => "EFI_WARN_STALE_DATA",
+            Self::WARN_FILE_SYSTEM => "EFI_WARN_FILE_SYSTEM",
+            Self::WARN_RESET_REQUIRED => "EFI_WARN_RESET_REQUIRED",
+            _ => return None
+        })
+    }
+}
+impl fmt::Debug for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "EFI_STATUS({:#x})", self.0)
+        }
+    }
+}
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+impl From<Error> for Status {
+    fn from(error: Error) -> Self {
+        error.status()
+    }
+}
+
+macro_rules! errors {
+    ($($code:literal $constant:ident $variant:ident;)*) => {
+        impl Status {
+            $(pub const $constant: Self = Self($code | Self::ERROR_BIT);)*
+        }
+
+        /// An EFI_STATUS with the error bit set
+        #[derive(Copy, Clone, PartialEq, Eq)]
+        // Variants follow the specification's names, such as EFI_LOAD_ERROR and EFI_CRC_ERROR
+        #[allow(clippy::enum_variant_names)]
+        pub enum Error {
+            $($variant,)*
+            /// An error code not defined by the specification, including the error bit
+            Unknown(usize)
+        }
+        impl Error {
+            pub fn status(self) -> Status {
+                match self {
+                    $(Self::$variant => Status::$constant,)*
+                    Self::Unknown(code) => Status(code)
+                }
+            }
+            fn from_status(status: Status) -> Self {
+                match status {
+                    $(Status::$constant => Self::$variant,)*
+                    Status(code) => Self::Unknown(code)
+                }
+            }
+            /// The name of the code in the UEFI specification
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => Some(concat!("EFI_", stringify!($constant))),)*
+                    Self::Unknown(_) => None
+                }
+            }
+        }
+    };
+}
+
+errors! {
+    1 LOAD_ERROR LoadError;
+    2 INVALID_PARAMETER InvalidParameter;
+    3 UNSUPPORTED Unsupported;
+    4 BAD_BUFFER_SIZE BadBufferSize;
+    5 BUFFER_TOO_SMALL BufferTooSmall;
+    6 NOT_READY NotReady;
+    7 DEVICE_ERROR DeviceError;
+    8 WRITE_PROTECTED WriteProtected;
+    9 OUT_OF_RESOURCES OutOfResources;
+    10 VOLUME_CORRUPTED VolumeCorrupted;
+    11 VOLUME_FULL VolumeFull;
+    12 NO_MEDIA NoMedia;
+    13 MEDIA_CHANGED MediaChanged;
+    14 NOT_FOUND NotFound;
+    15 ACCESS_DENIED AccessDenied;
+    16 NO_RESPONSE NoResponse;
+    17 NO_MAPPING NoMapping;
+    18 TIMEOUT Timeout;
+    19 NOT_STARTED NotStarted;
+    20 ALREADY_STARTED AlreadyStarted;
+    21 ABORTED Aborted;
+    22 ICMP_ERROR IcmpError;
+    23 TFTP_ERROR TftpError;
+    24 PROTOCOL_ERROR ProtocolError;
+    25 INCOMPATIBLE_VERSION IncompatibleVersion;
+    26 SECURITY_VIOLATION SecurityViolation;
+    27 CRC_ERROR CrcError;
+    28 END_OF_MEDIA EndOfMedia;
+    31 END_OF_FILE EndOfFile;
+    32 INVALID_LANGUAGE InvalidLanguage;
+    33 COMPROMISED_DATA CompromisedData;
+    34 IP_ADDRESS_CONFLICT IpAddressConflict;
+    35 HTTP_ERROR HttpError;
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.status(), f)
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.status(), f)
+    }
+}
@@ -0,0 +1,49 @@
+use bootinfo::{Framebuffer, PixelFormat};
+use crate::uefi::{self, protocol::graphics::{self, GraphicsOutput}};
+
+/// Switch to `preferred` if the firmware offers it, otherwise to the highest resolution mode with a linear framebuffer
+pub fn init(boot_services: &uefi::BootServices, preferred: Option<(u32, u32)>) -> Result<Framebuffer, uefi::Error> {
+    let graphics_output = boot_services.locate_protocol::<GraphicsOutput>()?;
+
+    let pixels = |info: &graphics::ModeInformation| info.width as u64 * info.height as u64;
+    let mut best: Option<(u32, graphics::ModeInformation)> = None;
+    for mode in 0..graphics_output.mode().max_mode {
+        let info = match graphics_output.query_mode(boot_services, mode) {
+            Ok(info) if linear(info.format) => info,
+            _ => continue
+        };
+        if preferred == Some((info.width, info.height)) {
+            best = Some((mode, info));
+            break
+        }
+        if best.is_none_or(|(_, best)| pixels(&info) > pixels(&best)) {
+            best = Some((mode, info));
+        }
+    }
+    let (mode, info) = best.ok_or(uefi::Error::Unsupported)?;
+    if graphics_output.mode().mode != mode {
+        graphics_output.set_mode(mode)?;
+    }
+
+    let current = graphics_output.mode();
+    Ok(Framebuffer {
+        base: current.framebuffer_base,
+        size: current.framebuffer_size as u64,
+        width: info.width,
+        height: info.height,
+        stride: info.stride,
+        format: match info.format {
+            graphics::PixelFormat::RGBX => PixelFormat::Rgbx,
+            graphics::PixelFormat::BGRX => PixelFormat::Bgrx,
+            _ => PixelFormat::Bitmask
+        },
+        red_mask: info.mask.red,
+        green_mask: info.mask.green,
+        blue_mask: info.mask.blue
+    })
+}
+
+/// Whether a mode has a framebuffer in a format the kernel is told how to draw to
+fn linear(format: graphics::PixelFormat) -> bool {
+    format == graphics::PixelFormat::RGBX || format == graphics::PixelFormat::BGRX || format == graphics::PixelFormat::BITMASK
+}
@@ -0,0 +1,471 @@
+//! The subset of the ELF64 format needed to load a statically linked or position-independent x86_64 kernel
+
+#![cfg_attr(not(test), no_std)]
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The image is smaller than the structures it claims to contain
+    Truncated,
+    /// Missing the `\x7FELF` magic number
+    Magic,
+    /// Not a little-endian ELF64 image
+    Class,
+    /// Not an x86_64 image
+    Machine,
+    /// Not an executable or position-independent executable
+    Type,
+    /// A loadable segment is malformed
+    Segment,
+    /// The dynamic section is malformed or needs a relocation other than `R_X86_64_RELATIVE`
+    Relocation
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Header {
+    pub ident: [u8; 16],
+    pub elf_type: u16,
+    pub machine: u16,
+    pub version: u32,
+    pub entry: u64,
+    pub program_header_offset: u64,
+    pub section_header_offset: u64,
+    pub flags: u32,
+    pub header_size: u16,
+    pub program_header_size: u16,
+    pub program_header_count: u16,
+    pub section_header_size: u16,
+    pub section_header_count: u16,
+    pub section_name_index: u16
+}
+impl Header {
+    pub const MAGIC: [u8; 4] = *b"\x7FELF";
+    pub const CLASS_64: u8 = 2;
+    pub const LITTLE_ENDIAN: u8 = 1;
+    pub const MACHINE_X86_64: u16 = 62;
+    pub const TYPE_EXECUTABLE: u16 = 2;
+    pub const TYPE_DYNAMIC: u16 = 3;
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ProgramHeader {
+    pub segment_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub virtual_address: u64,
+    pub physical_address: u64,
+    pub file_size: u64,
+    pub memory_size: u64,
+    pub align: u64
+}
+impl ProgramHeader {
+    pub const LOAD: u32 = 1;
+    pub const DYNAMIC: u32 = 2;
+
+    pub const EXECUTE: u32 = 0x1;
+    pub const WRITE: u32 = 0x2;
+    pub const READ: u32 = 0x4;
+
+    #[inline]
+    pub fn executable(&self) -> bool {
+        self.flags & Self::EXECUTE != 0
+    }
+    #[inline]
+    pub fn writable(&self) -> bool {
+        self.flags & Self::WRITE != 0
+    }
+}
+
+/// An entry of the PT_DYNAMIC segment
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Dynamic {
+    pub tag: i64,
+    pub value: u64
+}
+impl Dynamic {
+    pub const NULL: i64 = 0;
+    pub const RELA: i64 = 7;
+    pub const RELA_SIZE: i64 = 8;
+    pub const RELA_ENTRY_SIZE: i64 = 9;
+}
+
+/// A relocation with an explicit addend
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Rela {
+    pub offset: u64,
+    pub info: u64,
+    pub addend: i64
+}
+impl Rela {
+    /// Base address plus addend
+    pub const X86_64_RELATIVE: u32 = 8;
+
+    #[inline]
+    pub fn relocation_type(&self) -> u32 {
+        self.info as u32
+    }
+}
+
+/// A validated ELF64 image
+pub struct Elf<'a> {
+    data: &'a [u8],
+    header: Header
+}
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let header: Header = read(data, 0).ok_or(Error::Truncated)?;
+        if header.ident[..4] != Header::MAGIC {
+            return Err(Error::Magic)
+        }
+        if header.ident[4] != Header::CLASS_64 || header.ident[5] != Header::LITTLE_ENDIAN {
+            return Err(Error::Class)
+        }
+        if header.machine != Header::MACHINE_X86_64 {
+            return Err(Error::Machine)
+        }
+        if header.elf_type != Header::TYPE_EXECUTABLE && header.elf_type != Header::TYPE_DYNAMIC {
+            return Err(Error::Type)
+        }
+        if (header.program_header_size as usize) < core::mem::size_of::<ProgramHeader>() {
+            return Err(Error::Truncated)
+        }
+
+        let elf = Self { data, header };
+        for program_header in elf.program_headers() {
+            let program_header = program_header?;
+            if program_header.segment_type != ProgramHeader::LOAD {
+                continue
+            }
+            let file_end = program_header.offset.checked_add(program_header.file_size).ok_or(Error::Segment)?;
+            if file_end > data.len() as u64 {
+                return Err(Error::Truncated)
+            }
+            if program_header.file_size > program_header.memory_size
+                || program_header.virtual_address.checked_add(program_header.memory_size).is_none()
+                || program_header.virtual_address % 0x1000 != program_header.offset % 0x1000 {
+                return Err(Error::Segment)
+            }
+        }
+        Ok(elf)
+    }
+    #[inline]
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    #[inline]
+    pub fn entry(&self) -> u64 {
+        self.header.entry
+    }
+    pub fn program_headers(&self) -> ProgramHeaders<'a> {
+        ProgramHeaders {
+            data: self.data,
+            offset: self.header.program_header_offset as usize,
+            stride: self.header.program_header_size as usize,
+            remaining: self.header.program_header_count
+        }
+    }
+    /// The program headers of all PT_LOAD segments
+    pub fn segments(&self) -> impl Iterator<Item=ProgramHeader> + 'a {
+        self.program_headers()
+            .filter_map(Result::ok)
+            .filter(|program_header| program_header.segment_type == ProgramHeader::LOAD)
+    }
+    /// The bytes of a segment present in the file
+    pub fn segment_data(&self, program_header: &ProgramHeader) -> &'a [u8] {
+        &self.data[program_header.offset as usize..(program_header.offset + program_header.file_size) as usize]
+    }
+    /// Whether the image is a position-independent executable that can be loaded at any address
+    #[inline]
+    pub fn relocatable(&self) -> bool {
+        self.header.elf_type == Header::TYPE_DYNAMIC
+    }
+    /// The `Rela` relocations listed in the dynamic segment, which is empty for a static executable
+    pub fn relocations(&self) -> Result<Relocations<'a>, Error> {
+        let dynamic = match self.program_headers().filter_map(Result::ok).find(|program_header| program_header.segment_type == ProgramHeader::DYNAMIC) {
+            Some(dynamic) => dynamic,
+            None => return Ok(Relocations { data: self.data, offset: 0, remaining: 0 })
+        };
+
+        let (mut address, mut size, mut entry_size) = (None, 0, core::mem::size_of::<Rela>() as u64);
+        let entries = dynamic.file_size as usize / core::mem::size_of::<Dynamic>();
+        for i in 0..entries {
+            let entry: Dynamic = read(self.data, dynamic.offset as usize + i * core::mem::size_of::<Dynamic>()).ok_or(Error::Truncated)?;
+            match entry.tag {
+                Dynamic::NULL => break,
+                Dynamic::RELA => address = Some(entry.value),
+                Dynamic::RELA_SIZE => size = entry.value,
+                Dynamic::RELA_ENTRY_SIZE => entry_size = entry.value,
+                _ => ()
+            }
+        }
+        let address = match address {
+            Some(address) => address,
+            None => return Ok(Relocations { data: self.data, offset: 0, remaining: 0 })
+        };
+        if entry_size != core::mem::size_of::<Rela>() as u64 {
+            return Err(Error::Relocation)
+        }
+
+        // The table is located by its virtual address, so find the segment holding it to get its file offset
+        let offset = self.segments()
+            .find(|segment| address >= segment.virtual_address && address.saturating_add(size) <= segment.virtual_address + segment.file_size)
+            .map(|segment| address - segment.virtual_address + segment.offset)
+            .ok_or(Error::Relocation)?;
+        Ok(Relocations {
+            data: self.data,
+            offset: offset as usize,
+            remaining: (size / entry_size) as usize
+        })
+    }
+}
+
+pub struct Relocations<'a> {
+    data: &'a [u8],
+    offset: usize,
+    remaining: usize
+}
+impl<'a> Iterator for Relocations<'a> {
+    type Item = Result<Rela, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+        self.remaining -= 1;
+        let rela = read(self.data, self.offset).ok_or(Error::Truncated);
+        self.offset = self.offset.saturating_add(core::mem::size_of::<Rela>());
+        Some(rela)
+    }
+}
+
+pub struct ProgramHeaders<'a> {
+    data: &'a [u8],
+    offset: usize,
+    stride: usize,
+    remaining: u16
+}
+impl<'a> Iterator for ProgramHeaders<'a> {
+    type Item = Result<ProgramHeader, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None
+        }
+        self.remaining -= 1;
+        let program_header = read(self.data, self.offset).ok_or(Error::Truncated);
+        self.offset = self.offset.saturating_add(self.stride);
+        Some(program_header)
+    }
+}
+
+/// Read a plain old data structure from an unaligned offset
+fn read<T: Copy>(data: &[u8], offset: usize) -> Option<T> {
+    let end = offset.checked_add(core::mem::size_of::<T>())?;
+    if end > data.len() {
+        None
+    } else {
+        // Safe: bounds checked above and T is only instantiated with plain old data
+        Some(unsafe { core::ptr::read_unaligned(data.as_ptr().add(offset) as *const T) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_SIZE: usize = core::mem::size_of::<Header>();
+    const PROGRAM_HEADER_SIZE: usize = core::mem::size_of::<ProgramHeader>();
+
+    fn bytes<T: Copy>(value: &T) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>()) }
+    }
+
+    fn header(elf_type: u16, program_header_count: u16) -> Header {
+        let mut ident = [0; 16];
+        ident[..4].copy_from_slice(&Header::MAGIC);
+        ident[4] = Header::CLASS_64;
+        ident[5] = Header::LITTLE_ENDIAN;
+        Header {
+            ident,
+            elf_type,
+            machine: Header::MACHINE_X86_64,
+            version: 1,
+            entry: 0xFFFF_8000_0000_1000,
+            program_header_offset: HEADER_SIZE as u64,
+            section_header_offset: 0,
+            flags: 0,
+            header_size: HEADER_SIZE as u16,
+            program_header_size: PROGRAM_HEADER_SIZE as u16,
+            program_header_count,
+            section_header_size: 0,
+            section_header_count: 0,
+            section_name_index: 0
+        }
+    }
+
+    /// A LOAD segment of `file_size` bytes at the start of the second page of the file
+    fn segment(file_size: u64, memory_size: u64) -> ProgramHeader {
+        ProgramHeader {
+            segment_type: ProgramHeader::LOAD,
+            flags: ProgramHeader::READ | ProgramHeader::EXECUTE,
+            offset: 0x1000,
+            virtual_address: 0xFFFF_8000_0000_1000,
+            physical_address: 0,
+            file_size,
+            memory_size,
+            align: 0x1000
+        }
+    }
+
+    fn image(header: &Header, program_headers: &[ProgramHeader]) -> Vec<u8> {
+        let mut image = bytes(header).to_vec();
+        for program_header in program_headers {
+            image.extend_from_slice(bytes(program_header));
+        }
+        image.resize(0x1000, 0);
+        image.extend((0..0x100).map(|i| i as u8));
+        image
+    }
+
+    #[test]
+    fn parse() {
+        let image = image(&header(Header::TYPE_EXECUTABLE, 1), &[segment(0x100, 0x2000)]);
+        let elf = Elf::parse(&image).unwrap();
+        assert_eq!(elf.entry(), 0xFFFF_8000_0000_1000);
+        assert!(!elf.relocatable());
+
+        let segments: Vec<_> = elf.segments().collect();
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].executable());
+        assert!(!segments[0].writable());
+        let data = elf.segment_data(&segments[0]);
+        assert_eq!(data.len(), 0x100);
+        assert_eq!(data[0x42], 0x42);
+    }
+
+    #[test]
+    fn relocatable() {
+        let image = image(&header(Header::TYPE_DYNAMIC, 1), &[segment(0x100, 0x100)]);
+        assert!(Elf::parse(&image).unwrap().relocatable());
+    }
+
+    #[test]
+    fn skips_other_segments() {
+        let mut note = segment(0x100, 0x100);
+        note.segment_type = 4;
+        let image = image(&header(Header::TYPE_EXECUTABLE, 2), &[note, segment(0x80, 0x80)]);
+        let elf = Elf::parse(&image).unwrap();
+        assert_eq!(elf.program_headers().count(), 2);
+        let segments: Vec<_> = elf.segments().collect();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].file_size, 0x80);
+    }
+
+    #[test]
+    fn invalid_header() {
+        let valid = header(Header::TYPE_EXECUTABLE, 0);
+        assert_eq!(Elf::parse(&bytes(&valid)[..HEADER_SIZE - 1]).err(), Some(Error::Truncated));
+
+        let mut header = valid;
+        header.ident[0] = 0;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Magic));
+        let mut header = valid;
+        header.ident[4] = 1;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Class));
+        let mut header = valid;
+        header.ident[5] = 2;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Class));
+        let mut header = valid;
+        header.machine = 3;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Machine));
+        let mut header = valid;
+        header.elf_type = 1;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Type));
+        let mut header = valid;
+        header.program_header_size = PROGRAM_HEADER_SIZE as u16 - 1;
+        assert_eq!(Elf::parse(bytes(&header)).err(), Some(Error::Truncated));
+    }
+
+    #[test]
+    fn truncated_program_headers() {
+        let image = image(&header(Header::TYPE_EXECUTABLE, 1), &[segment(0x100, 0x100)]);
+        assert_eq!(Elf::parse(&image[..HEADER_SIZE + PROGRAM_HEADER_SIZE - 1]).err(), Some(Error::Truncated));
+    }
+
+    #[test]
+    fn invalid_segment() {
+        let header = header(Header::TYPE_EXECUTABLE, 1);
+        let parse = |segment| Elf::parse(&image(&header, &[segment])).err();
+
+        assert_eq!(parse(segment(0x101, 0x1000)), Some(Error::Truncated));
+        assert_eq!(parse(segment(u64::MAX, u64::MAX)), Some(Error::Segment));
+        assert_eq!(parse(segment(0x100, 0x80)), Some(Error::Segment));
+        let mut overflowing = segment(0x100, 0x1000);
+        overflowing.virtual_address = u64::MAX - 0xFFF;
+        assert_eq!(parse(overflowing), Some(Error::Segment));
+        let mut misaligned = segment(0x100, 0x100);
+        misaligned.virtual_address += 8;
+        assert_eq!(parse(misaligned), Some(Error::Segment));
+    }
+    /// A position-independent image whose segment holds a dynamic table followed by `relocations`
+    fn dynamic_image(entry_size: u64, relocations: &[Rela]) -> Vec<u8> {
+        const BASE: u64 = 0xFFFF_8000_0000_1000;
+        let table_size = core::mem::size_of_val(relocations) as u64;
+        let dynamic_table = [
+            Dynamic { tag: Dynamic::RELA, value: BASE + 0x40 },
+            Dynamic { tag: Dynamic::RELA_SIZE, value: table_size },
+            Dynamic { tag: Dynamic::RELA_ENTRY_SIZE, value: entry_size },
+            Dynamic { tag: Dynamic::NULL, value: 0 }
+        ];
+        let mut dynamic = segment(0x40, 0x40);
+        dynamic.segment_type = ProgramHeader::DYNAMIC;
+
+        let mut image = image(&header(Header::TYPE_DYNAMIC, 2), &[segment(0x100, 0x100), dynamic]);
+        image.resize(image.len().max(0x1040 + table_size as usize), 0);
+        let mut offset = 0x1000;
+        for entry in &dynamic_table {
+            image[offset..offset + core::mem::size_of::<Dynamic>()].copy_from_slice(bytes(entry));
+            offset += core::mem::size_of::<Dynamic>();
+        }
+        for rela in relocations {
+            image[offset..offset + core::mem::size_of::<Rela>()].copy_from_slice(bytes(rela));
+            offset += core::mem::size_of::<Rela>();
+        }
+        image
+    }
+
+    #[test]
+    fn relocations() {
+        let relocations = [
+            Rela { offset: 0xFFFF_8000_0000_1080, info: Rela::X86_64_RELATIVE as u64, addend: 0x1234 },
+            Rela { offset: 0xFFFF_8000_0000_1088, info: 1, addend: -8 }
+        ];
+        let image = dynamic_image(core::mem::size_of::<Rela>() as u64, &relocations);
+        let elf = Elf::parse(&image).unwrap();
+        let parsed: Vec<_> = elf.relocations().unwrap().map(Result::unwrap).collect();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].relocation_type(), Rela::X86_64_RELATIVE);
+        assert_eq!(parsed[0].offset, 0xFFFF_8000_0000_1080);
+        assert_eq!(parsed[0].addend, 0x1234);
+        assert_eq!(parsed[1].relocation_type(), 1);
+        assert_eq!(parsed[1].addend, -8);
+    }
+
+    #[test]
+    fn no_relocations() {
+        let image = image(&header(Header::TYPE_EXECUTABLE, 1), &[segment(0x100, 0x100)]);
+        assert_eq!(Elf::parse(&image).unwrap().relocations().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn invalid_relocations() {
+        let image = dynamic_image(16, &[]);
+        assert_eq!(Elf::parse(&image).unwrap().relocations().err(), Some(Error::Relocation));
+
+        // A table that runs past the end of the segment it starts in
+        let relocations = [Rela { offset: 0, info: Rela::X86_64_RELATIVE as u64, addend: 0 }; 9];
+        let image = dynamic_image(core::mem::size_of::<Rela>() as u64, &relocations);
+        assert_eq!(Elf::parse(&image).unwrap().relocations().err(), Some(Error::Relocation));
+    }
+}
@@ -0,0 +1,13 @@
+#![no_std]
+#![no_main]
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop { }
+}
+
+/// The first program the kernel runs, unpacked from the initrd
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    loop { }
+}
@@ -48,7 +48,7 @@ impl Allocator {
                 ..
             } = segment{
                 for i in 0..count {
-                    let page = page.add(i * core::mem::size_of::<Page>());
+                    let page = page.add(i);
                     self.reclaim(page)
                 }
             }
@@ -68,31 +68,63 @@ impl Allocator {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
 pub struct MemorySegment {
     pub page: *mut Page,
     pub count: usize,
     pub usage: MemoryUsage,
     pub properties: MemoryProperties
-} 
+}
+impl MemorySegment {
+    /// The address of the first byte after the segment
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.page as u64 + (self.count * core::mem::size_of::<Page>()) as u64
+    }
+}
 
 use core::ops::{Deref, DerefMut};
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum MemoryUsage {
     Reserved,
     Allocator,
     Free,
     Unusable,
-    Mmio
+    Mmio,
+    /// In use during boot but may be freed once the kernel is done with its contents
+    Reclaimable,
+    /// The kernel image, its stack and page tables
+    Kernel,
+    /// The initial ramdisk, kept until the kernel has unpacked it
+    Initrd
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(transparent)]
 pub struct MemoryProperties(u32);
 impl MemoryProperties {
     pub const READ: u32 = 1 << 0;
     pub const WRITE: u32 = 1 << 1;
     pub const EXECUTE: u32 = 1 << 2;
+    /// The memory can be mapped uncached
+    pub const UNCACHEABLE: u32 = 1 << 3;
+    /// The memory can be mapped write-combining
+    pub const WRITE_COMBINING: u32 = 1 << 4;
+    /// The memory can be mapped write-through
+    pub const WRITE_THROUGH: u32 = 1 << 5;
+    /// The memory can be mapped write-back
+    pub const WRITE_BACK: u32 = 1 << 6;
+    pub const NON_VOLATILE: u32 = 1 << 7;
+    /// The firmware needs the memory mapped for runtime services
+    pub const RUNTIME: u32 = 1 << 8;
+
+    #[inline]
+    pub const fn new(bits: u32) -> Self {
+        Self(bits)
+    }
 
     pub fn all(self, bits: Self) -> bool {
         *self & *bits == *bits
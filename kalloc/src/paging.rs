@@ -90,6 +90,11 @@ impl DerefMut for VirtualAddress {
         &mut self.0
     }
 }
+impl From<u64> for VirtualAddress {
+    fn from(address: u64) -> Self {
+        Self(address)
+    }
+}
 impl<T> From<*mut T> for VirtualAddress {
     fn from(r: *mut T) -> Self {
         Self(r as _)
@@ -193,9 +198,49 @@ pub mod page {
             Some(&mut (*table)[address])
         }
         #[inline(always)]
-        /// Get the exact physical address for a virtual address
+        /// Get the exact physical address for a virtual address, or null if it is not mapped
         pub unsafe fn physical<T>(&self, address: VirtualAddress) -> *mut T {
-            self.page(address).add(address.offset()) as _
+            let page = self.page(address);
+            if page.is_null() { return null_mut() }
+            (page as *mut u8).add(address.offset()) as _
+        }
+        /// Map a virtual address to a page, creating any missing page tables with `allocate`.
+        /// Intermediate tables are mapped present and writable so that the returned entry alone decides the permissions.
+        /// Returns `None` if `allocate` fails or the address is already mapped.
+        /// # Safety
+        /// The page tables must be identity mapped and `allocate` must return zeroed pages or null.
+        pub unsafe fn map(&mut self, address: VirtualAddress, page: *mut Page, mut allocate: impl FnMut() -> *mut Page) -> Option<&mut Level1Entry> {
+            let level4 = &mut self[address];
+            if !level4.present() {
+                let table = allocate();
+                if table.is_null() { return None }
+                level4.set_address(table as _);
+                level4.set_write();
+                level4.set_present();
+            }
+            let level3 = &mut (*level4.address())[address];
+            if !level3.present() {
+                let table = allocate();
+                if table.is_null() { return None }
+                level3.set_address(table as _);
+                level3.set_write();
+                level3.set_present();
+            }
+            let level2 = &mut (*level3.address())[address];
+            if !level2.present() {
+                let table = allocate();
+                if table.is_null() { return None }
+                level2.set_address(table as _);
+                level2.set_write();
+                level2.set_present();
+            }
+            let level1 = &mut (*level2.address())[address];
+            if level1.present() {
+                return None
+            }
+            level1.set_address(page);
+            level1.set_present();
+            Some(level1)
         }
     }
 
@@ -276,6 +321,18 @@ pub mod page {
         pub fn unset_accessed(&mut self) {
             self.0 &= !0x20
         }
+        #[inline(always)]
+        pub fn no_execute(self) -> bool {
+            (self.0 & 0x8000_0000_0000_0000) != 0
+        }
+        #[inline(always)]
+        pub fn set_no_execute(&mut self) {
+            self.0 |= 0x8000_0000_0000_0000
+        }
+        #[inline(always)]
+        pub fn unset_no_execute(&mut self) {
+            self.0 &= !0x8000_0000_0000_0000
+        }
     }
     /// A Page Mode Level-4 Entry (PML4E)
     #[derive(Copy, Clone, Default, Debug)]
@@ -301,6 +358,12 @@ pub mod page {
             unsafe { core::mem::transmute(self) }
         }
     }
+    impl DerefMut for Level4Entry {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            // Safe: page::Pointer and page::Level4Entry have the exact same layout, including compatible lifetimes
+            unsafe { core::mem::transmute(self) }
+        }
+    }
     /// A Page Directory Pointer Entry (PDPTE)
     #[derive(Copy, Clone, Default, Debug)]
     #[repr(transparent)]
@@ -325,6 +388,12 @@ pub mod page {
             unsafe { core::mem::transmute(self) }
         }
     }
+    impl DerefMut for Level3Entry {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            // Safe: page::Pointer and page::Level3Entry have the exact same layout, including compatible lifetimes
+            unsafe { core::mem::transmute(self) }
+        }
+    }
     /// A Page Directory Entry (PDE)
     #[derive(Copy, Clone, Default, Debug)]
     #[repr(transparent)]
@@ -349,6 +418,12 @@ pub mod page {
             unsafe { core::mem::transmute(self) }
         }
     }
+    impl DerefMut for Level2Entry {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            // Safe: page::Pointer and page::Level2Entry have the exact same layout, including compatible lifetimes
+            unsafe { core::mem::transmute(self) }
+        }
+    }
     /// A Page Entry (PTE)
     #[derive(Copy, Clone, Default, Debug)]
     #[repr(transparent)]
@@ -373,4 +448,10 @@ pub mod page {
             unsafe { core::mem::transmute(self) }
         }
     }
+    impl DerefMut for Level1Entry {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            // Safe: page::Pointer and page::Level1Entry have the exact same layout, including compatible lifetimes
+            unsafe { core::mem::transmute(self) }
+        }
+    }
 }
\ No newline at end of file
@@ -3,14 +3,34 @@
 
 #![feature(asm)]
 
+use core::fmt::Write;
+use bootinfo::BootInfo;
+use serial::Serial;
+
 #[panic_handler]
-fn panic(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let _ = writeln!(Serial::new(Serial::COM1), "\nKernel {}", info);
+    halt()
 }
 
 /// Although we are already within Rust, kernel() must use a stable ABI as the uefi-stub is a seperate compilation unit
 #[no_mangle]
-pub extern "C" fn kernel(allocator: kalloc::Allocator) -> ! {
-    
+pub extern "C" fn kernel(boot_info: *const BootInfo, allocator: kalloc::Allocator) -> ! {
+    // Nothing else in the boot info can be trusted until the version is known to match
+    let _boot_info = match unsafe { BootInfo::validate(boot_info) } {
+        Ok(boot_info) => boot_info,
+        Err(error) => {
+            let _ = writeln!(Serial::new(Serial::COM1), "cherimoya: refusing to boot: {}", error);
+            halt()
+        }
+    };
+
     loop { }
-}
\ No newline at end of file
+}
+
+/// Stop this processor forever
+fn halt() -> ! {
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) }
+    }
+}
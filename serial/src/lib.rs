@@ -0,0 +1,55 @@
+#![no_std]
+
+#![feature(asm)]
+
+use core::fmt;
+
+/// A 16550 UART accessed through port I/O, usable by the bootloader after boot services have exited and by the kernel
+pub struct Serial(u16);
+impl Serial {
+    pub const COM1: u16 = 0x3F8;
+
+    /// Configure the port for 115200 baud, 8 data bits, no parity and one stop bit
+    pub fn new(port: u16) -> Self {
+        unsafe {
+            // Disable interrupts
+            outb(port + 1, 0x00);
+            // Set the divisor latch to 1
+            outb(port + 3, 0x80);
+            outb(port, 0x01);
+            outb(port + 1, 0x00);
+            // 8N1
+            outb(port + 3, 0x03);
+            // Enable and clear the FIFOs
+            outb(port + 2, 0xC7);
+        }
+        Self(port)
+    }
+    pub fn write_byte(&mut self, byte: u8) {
+        unsafe {
+            // Wait for the transmit holding register to empty
+            while inb(self.0 + 5) & 0x20 == 0 {}
+            outb(self.0, byte)
+        }
+    }
+}
+impl fmt::Write for Serial {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r')
+            }
+            self.write_byte(byte)
+        }
+        Ok(())
+    }
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack, preserves_flags))
+}
+unsafe fn inb(port: u16) -> u8 {
+    let value;
+    asm!("in al, dx", in("dx") port, out("al") value, options(nomem, nostack, preserves_flags));
+    value
+}